@@ -17,7 +17,10 @@ use wgpu_rust_renderer::{
 		camera::PerspectiveCamera,
 		mesh::Mesh,
 		node::Node,
-		scene::Scene,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
 	},
 	utils::{
 		geometry_helper::GeometryHelper,
@@ -114,10 +117,7 @@ fn update(
 		node.borrow_rotation_mut()[1] += 0.01;
 	}
 
-	pools.borrow::<Scene>()
-		.borrow(scene)
-		.unwrap()
-		.update_matrices(pools);
+	SceneExecutor::update_matrices(pools, scene).unwrap();
 }
 
 fn render(
@@ -126,7 +126,7 @@ fn render(
 	scene: &ResourceId<Scene>,
 	camera: &ResourceId<PerspectiveCamera>,
 ) {
-	renderer.render(pools, scene, camera);
+	renderer.render(pools, scene, camera).unwrap();
 }
 
 #[tokio::main]
@@ -137,7 +137,7 @@ async fn main() {
 	let window_size = window.inner_size();
 	let pixel_ratio = window.scale_factor();
 
-	let mut renderer = WGPURenderer::new(&window, WGPURendererOptions::default()).await;
+	let mut renderer = WGPURenderer::new(&window, WGPURendererOptions::default()).await.unwrap();
 	renderer.set_size(window_size.width as f64, window_size.height as f64);
 	renderer.set_pixel_ratio(pixel_ratio);
 
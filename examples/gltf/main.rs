@@ -16,65 +16,25 @@ use wgpu_rust_renderer::{
 	scene::{
 		camera::PerspectiveCamera,
 		node::Node,
-		scene::Scene,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
 	},
-	utils::gltf_loader::GltfLoader,
 };
 
+#[path = "../scenes/mod.rs"]
+mod scenes;
+
 async fn create_scene(
 	window: &Window,
 	pools: &mut ResourcePools
 ) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
-	let mut objects = Vec::new();
-	let scene_rid = pools.borrow_mut::<Scene>().add(Scene::new());
-
-	let nodes = GltfLoader::load_gltf(
-		pools,
-		&scene_rid,
-		concat!(
-			env!("CARGO_MANIFEST_DIR"),
-			"/examples/gltf/assets/",
-		),
-		"DamagedHelmet.gltf",
-	).await;
-
-	for node in nodes.iter() {
-		pools.borrow_mut::<Scene>()
-			.borrow_mut(&scene_rid)
-			.unwrap()
-			.add_node(node);
-		objects.push(*node);
-		pools.borrow_mut::<Node>()
-			.borrow_mut(node)
-			.unwrap()
-			.borrow_rotation_mut()[0] = 90.0_f32.to_radians();
-	}
-
 	let window_size = window.inner_size();
-	let camera = pools.borrow_mut::<PerspectiveCamera>().add(
-		PerspectiveCamera::new(
-			60.0_f32.to_radians(),
-			window_size.width as f32 / window_size.height as f32,
-			0.1,
-			1000.0,
-		),
-	);
-
-	let mut node = Node::new();
-	Vector3::set(
-		node.borrow_position_mut(),
-		0.0, 0.0, 3.0,
-	);
-
-	let node = pools.borrow_mut::<Node>().add(node);
-
-	{
-		let scene = pools.borrow_mut::<Scene>().borrow_mut(&scene_rid).unwrap();
-		scene.add_node(&node);
-		scene.assign(&node, &camera);
-	}
-
-	(scene_rid, camera, objects)
+	scenes::create_gltf_scene(
+		pools,
+		window_size.width as f32 / window_size.height as f32,
+	).await
 }
 
 fn resize(
@@ -105,10 +65,7 @@ fn update(
 		);
 	}
 
-	pools.borrow::<Scene>()
-		.borrow(scene)
-		.unwrap()
-		.update_matrices(pools);
+	SceneExecutor::update_matrices(pools, scene).unwrap();
 }
 
 fn render(
@@ -117,7 +74,7 @@ fn render(
 	scene: &ResourceId<Scene>,
 	camera: &ResourceId<PerspectiveCamera>,
 ) {
-	renderer.render(pools, scene, camera);
+	renderer.render(pools, scene, camera).unwrap();
 }
 
 #[tokio::main]
@@ -128,7 +85,7 @@ async fn main() {
 	let window_size = window.inner_size();
 	let pixel_ratio = window.scale_factor();
 
-	let mut renderer = WGPURenderer::new(&window, WGPURendererOptions::default()).await;
+	let mut renderer = WGPURenderer::new(&window, WGPURendererOptions::default()).await.unwrap();
 	renderer.set_size(window_size.width as f64, window_size.height as f64);
 	renderer.set_pixel_ratio(pixel_ratio);
 
@@ -4,10 +4,7 @@ use winit::{
 	window::Window,
 };
 use wgpu_rust_renderer::{
-	math::{
-		color::Color,
-		vector3::Vector3,
-	},
+	math::vector3::Vector3,
 	renderer::wgpu_renderer::{
 		WGPURenderer,
 		WGPURendererOptions,
@@ -18,64 +15,26 @@ use wgpu_rust_renderer::{
 	},
 	scene::{
 		camera::PerspectiveCamera,
-		mesh::Mesh,
 		node::Node,
-		scene::Scene,
-	},
-	utils::{
-		geometry_helper::GeometryHelper,
-		material_helper::MaterialHelper,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
 	},
 };
 
+#[path = "../scenes/mod.rs"]
+mod scenes;
+
 fn create_scene(
 	window: &Window,
 	pools: &mut ResourcePools
 ) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
-	let mut objects = Vec::new();
-	let mut scene = Scene::new();
-
-	let geometry = GeometryHelper::create_box(
-		pools,
-		1.0,
-		1.0,
-		1.0,
-	);
-
-	let material = MaterialHelper::create_brdf_material(
-		pools,
-		Color::set(&mut Color::create(), 1.0, 1.0, 1.0),
-		0.5,
-		0.5,
-	);
-
-	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
-	let node = pools.borrow_mut::<Node>().add(Node::new());
-	scene.add_node(&node);
-	scene.assign(&node, &mesh);
-	objects.push(node);
-
 	let window_size = window.inner_size();
-	let camera = pools.borrow_mut::<PerspectiveCamera>().add(
-		PerspectiveCamera::new(
-			60.0_f32.to_radians(),
-			window_size.width as f32 / window_size.height as f32,
-			0.1,
-			1000.0,
-		),
-	);
-
-	let mut node = Node::new();
-	Vector3::set(
-		node.borrow_position_mut(),
-		0.0, 0.0, 3.0,
-	);
-
-	let node = pools.borrow_mut::<Node>().add(node);
-	scene.add_node(&node);
-	scene.assign(&node, &camera);
-
-	(pools.borrow_mut::<Scene>().add(scene), camera, objects)
+	scenes::create_pbr_scene(
+		pools,
+		window_size.width as f32 / window_size.height as f32,
+	)
 }
 
 fn resize(
@@ -106,10 +65,7 @@ fn update(
 		);
 	}
 
-	pools.borrow::<Scene>()
-		.borrow(scene)
-		.unwrap()
-		.update_matrices(pools);
+	SceneExecutor::update_matrices(pools, scene).unwrap();
 }
 
 fn render(
@@ -118,7 +74,7 @@ fn render(
 	scene: &ResourceId<Scene>,
 	camera: &ResourceId<PerspectiveCamera>,
 ) {
-	renderer.render(pools, scene, camera);
+	renderer.render(pools, scene, camera).unwrap();
 }
 
 #[tokio::main]
@@ -129,7 +85,7 @@ async fn main() {
 	let window_size = window.inner_size();
 	let pixel_ratio = window.scale_factor();
 
-	let mut renderer = WGPURenderer::new(&window, WGPURendererOptions::default()).await;
+	let mut renderer = WGPURenderer::new(&window, WGPURendererOptions::default()).await.unwrap();
 	renderer.set_size(window_size.width as f64, window_size.height as f64);
 	renderer.set_pixel_ratio(pixel_ratio);
 
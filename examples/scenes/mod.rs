@@ -0,0 +1,236 @@
+// Scenes of the examples. Shared with the golden image tests so that
+// the tests render what the examples show. Include it with
+// #[path = "../scenes/mod.rs"] mod scenes;
+//
+// Each function returns the scene, the camera, and the nodes the
+// examples animate.
+
+#![allow(dead_code)]
+
+use wgpu_rust_renderer::{
+	math::{
+		color::Color,
+		vector3::Vector3,
+	},
+	resource::resource::{
+		ResourceId,
+		ResourcePools,
+	},
+	scene::{
+		camera::PerspectiveCamera,
+		mesh::Mesh,
+		node::Node,
+		scene::Scene,
+	},
+	texture::texture::TextureFormat,
+	utils::{
+		geometry_helper::GeometryHelper,
+		gltf_loader::GltfLoader,
+		material_helper::MaterialHelper,
+		texture_loader::TextureLoader,
+	},
+};
+
+pub fn create_camera(
+	pools: &mut ResourcePools,
+	aspect: f32,
+	z: f32,
+) -> (ResourceId<PerspectiveCamera>, ResourceId<Node>) {
+	let camera = pools.borrow_mut::<PerspectiveCamera>().add(
+		PerspectiveCamera::new(
+			60.0_f32.to_radians(),
+			aspect,
+			0.1,
+			1000.0,
+		),
+	);
+
+	let mut node = Node::new();
+	Vector3::set(
+		node.borrow_position_mut(),
+		0.0, 0.0, z,
+	);
+
+	(camera, pools.borrow_mut::<Node>().add(node))
+}
+
+pub fn create_triangle_scene(
+	pools: &mut ResourcePools,
+	aspect: f32,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
+	let mut objects = Vec::new();
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_triangle(
+		pools,
+		1.0,
+		1.0,
+	);
+
+	let material = MaterialHelper::create_basic_material(
+		pools,
+		Color::set(&mut Color::create(), 1.0, 0.0, 0.0),
+	);
+
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+	let node = pools.borrow_mut::<Node>().add(Node::new());
+	scene.add_node(&node);
+	scene.assign(&node, &mesh);
+	objects.push(node);
+
+	let (camera, node) = create_camera(pools, aspect, 1.0);
+	scene.add_node(&node);
+	scene.assign(&node, &camera);
+
+	(pools.borrow_mut::<Scene>().add(scene), camera, objects)
+}
+
+pub fn create_cube_scene(
+	pools: &mut ResourcePools,
+	aspect: f32,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
+	let mut objects = Vec::new();
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_box(
+		pools,
+		1.0,
+		1.0,
+		1.0,
+	);
+
+	let material = MaterialHelper::create_basic_material(
+		pools,
+		Color::set(&mut Color::create(), 0.5, 0.5, 1.0),
+	);
+
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+	let node = pools.borrow_mut::<Node>().add(Node::new());
+	scene.add_node(&node);
+	scene.assign(&node, &mesh);
+	objects.push(node);
+
+	let (camera, node) = create_camera(pools, aspect, 3.0);
+	scene.add_node(&node);
+	scene.assign(&node, &camera);
+
+	(pools.borrow_mut::<Scene>().add(scene), camera, objects)
+}
+
+pub async fn create_texture_scene(
+	pools: &mut ResourcePools,
+	aspect: f32,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
+	let mut objects = Vec::new();
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_box(
+		pools,
+		1.0,
+		1.0,
+		1.0,
+	);
+
+	let texture = TextureLoader::load_png_with_filepath(
+		pools,
+		concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/examples/texture/texture.png",
+		),
+		TextureFormat::default(),
+	).await.unwrap();
+
+	let material = MaterialHelper::create_basic_material_with_texture(
+		pools,
+		Color::set(&mut Color::create(), 0.5, 0.5, 1.0),
+		texture,
+	);
+
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+	let mut node = Node::new();
+	node.borrow_rotation_mut()[0] = 35.0_f32.to_radians();
+	let node = pools.borrow_mut::<Node>().add(node);
+	scene.add_node(&node);
+	scene.assign(&node, &mesh);
+	objects.push(node);
+
+	let (camera, node) = create_camera(pools, aspect, 3.0);
+	scene.add_node(&node);
+	scene.assign(&node, &camera);
+
+	(pools.borrow_mut::<Scene>().add(scene), camera, objects)
+}
+
+pub fn create_pbr_scene(
+	pools: &mut ResourcePools,
+	aspect: f32,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
+	let mut objects = Vec::new();
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_box(
+		pools,
+		1.0,
+		1.0,
+		1.0,
+	);
+
+	let material = MaterialHelper::create_brdf_material(
+		pools,
+		Color::set(&mut Color::create(), 1.0, 1.0, 1.0),
+		0.5,
+		0.5,
+	);
+
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+	let node = pools.borrow_mut::<Node>().add(Node::new());
+	scene.add_node(&node);
+	scene.assign(&node, &mesh);
+	objects.push(node);
+
+	let (camera, node) = create_camera(pools, aspect, 3.0);
+	scene.add_node(&node);
+	scene.assign(&node, &camera);
+
+	(pools.borrow_mut::<Scene>().add(scene), camera, objects)
+}
+
+pub async fn create_gltf_scene(
+	pools: &mut ResourcePools,
+	aspect: f32,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, Vec<ResourceId<Node>>) {
+	let mut objects = Vec::new();
+	let scene_rid = pools.borrow_mut::<Scene>().add(Scene::new());
+
+	let nodes = GltfLoader::load_gltf(
+		pools,
+		&scene_rid,
+		concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/examples/gltf/assets/",
+		),
+		"DamagedHelmet.gltf",
+	).await.unwrap();
+
+	for node in nodes.iter() {
+		pools.borrow_mut::<Scene>()
+			.borrow_mut(&scene_rid)
+			.unwrap()
+			.add_node(node);
+		objects.push(*node);
+		pools.borrow_mut::<Node>()
+			.borrow_mut(node)
+			.unwrap()
+			.borrow_rotation_mut()[0] = 90.0_f32.to_radians();
+	}
+
+	let (camera, node) = create_camera(pools, aspect, 3.0);
+
+	{
+		let scene = pools.borrow_mut::<Scene>().borrow_mut(&scene_rid).unwrap();
+		scene.add_node(&node);
+		scene.assign(&node, &camera);
+	}
+
+	(scene_rid, camera, objects)
+}
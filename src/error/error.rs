@@ -0,0 +1,127 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+	Base64(base64::DecodeError),
+	// Failed to map a buffer to read it back on CPU
+	BufferAsync(wgpu::BufferAsyncError),
+	// Failed to fetch a file over network (Wasm)
+	Fetch(String),
+	Gltf(gltf::Error),
+	// Node would become its own ancestor
+	HierarchyCycle,
+	// Asset is readable but its contents are not what we expect
+	InvalidAsset(String),
+	Io(std::io::Error),
+	Jpeg(jpeg_decoder::Error),
+	NoAdapter,
+	// Mesh needs at least one material
+	NoMaterial,
+	PngDecoding(png::DecodingError),
+	PngEncoding(png::EncodingError),
+	RequestDevice(wgpu::RequestDeviceError),
+	ResourceNotFound(&'static str),
+	Surface(wgpu::SurfaceError),
+	UnknownImageFormat(String),
+	UnknownResourceType(&'static str),
+	// Valid input but not supported by this library yet
+	Unsupported(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Base64(e) => write!(f, "Base64 decoding error: {}", e),
+			Error::BufferAsync(e) => write!(f, "Buffer mapping error: {}", e),
+			Error::Fetch(message) => write!(f, "Failed to fetch: {}", message),
+			Error::Gltf(e) => write!(f, "glTF error: {}", e),
+			Error::HierarchyCycle => write!(f, "Node can't be a descendant of itself"),
+			Error::InvalidAsset(message) => write!(f, "Invalid asset: {}", message),
+			Error::Io(e) => write!(f, "I/O error: {}", e),
+			Error::Jpeg(e) => write!(f, "JPEG decoding error: {}", e),
+			Error::NoAdapter => write!(f, "Failed to find an appropriate adapter"),
+			Error::NoMaterial => write!(f, "Mesh needs at least one material"),
+			Error::PngDecoding(e) => write!(f, "PNG decoding error: {}", e),
+			Error::PngEncoding(e) => write!(f, "PNG encoding error: {}", e),
+			Error::RequestDevice(e) => write!(f, "Failed to create device: {}", e),
+			Error::ResourceNotFound(name) => write!(f, "{} is not found in the resource pool", name),
+			Error::Surface(e) => write!(f, "Surface error: {}", e),
+			Error::UnknownImageFormat(path) => write!(f, "Unknown image format: {}", path),
+			Error::UnknownResourceType(name) => write!(f, "Unknown resource type: {}", name),
+			Error::Unsupported(message) => write!(f, "Unsupported: {}", message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Base64(e) => Some(e),
+			Error::BufferAsync(e) => Some(e),
+			Error::Gltf(e) => Some(e),
+			Error::Io(e) => Some(e),
+			Error::Jpeg(e) => Some(e),
+			Error::PngDecoding(e) => Some(e),
+			Error::PngEncoding(e) => Some(e),
+			Error::RequestDevice(e) => Some(e),
+			Error::Surface(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<base64::DecodeError> for Error {
+	fn from(e: base64::DecodeError) -> Self {
+		Error::Base64(e)
+	}
+}
+
+impl From<wgpu::BufferAsyncError> for Error {
+	fn from(e: wgpu::BufferAsyncError) -> Self {
+		Error::BufferAsync(e)
+	}
+}
+
+impl From<gltf::Error> for Error {
+	fn from(e: gltf::Error) -> Self {
+		Error::Gltf(e)
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(e: std::io::Error) -> Self {
+		Error::Io(e)
+	}
+}
+
+impl From<jpeg_decoder::Error> for Error {
+	fn from(e: jpeg_decoder::Error) -> Self {
+		Error::Jpeg(e)
+	}
+}
+
+impl From<png::DecodingError> for Error {
+	fn from(e: png::DecodingError) -> Self {
+		Error::PngDecoding(e)
+	}
+}
+
+impl From<png::EncodingError> for Error {
+	fn from(e: png::EncodingError) -> Self {
+		Error::PngEncoding(e)
+	}
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+	fn from(e: wgpu::RequestDeviceError) -> Self {
+		Error::RequestDevice(e)
+	}
+}
+
+impl From<wgpu::SurfaceError> for Error {
+	fn from(e: wgpu::SurfaceError) -> Self {
+		Error::Surface(e)
+	}
+}
@@ -1,23 +1,170 @@
+// Component type of attribute data
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub enum AttributeType {
+	// IEEE 754 half precision floats stored as bits
+	Float16,
+	Float32,
+	Sint8,
+	Sint16,
+	Uint8,
+	Uint16,
+	Uint32,
+}
+
+impl AttributeType {
+	// In bytes
+	pub fn get_size(&self) -> u32 {
+		match self {
+			AttributeType::Sint8 |
+			AttributeType::Uint8 => 1,
+			AttributeType::Float16 |
+			AttributeType::Sint16 |
+			AttributeType::Uint16 => 2,
+			AttributeType::Float32 |
+			AttributeType::Uint32 => 4,
+		}
+	}
+
+	// Components per item in GPU buffers and shaders. 8-bit and 16-bit
+	// data with item size 1 or 3 are padded with zero components because
+	// vertex formats of them have only 2 or 4 components.
+	pub fn get_padded_item_size(&self, item_size: u32) -> u32 {
+		match (self.get_size(), item_size) {
+			(1, 2) | (2, 1) | (2, 2) => 2,
+			(1, _) | (2, _) => 4,
+			(_, item_size) => item_size,
+		}
+	}
+
+	// Bytes per item in GPU buffers, the padded item size rounded up to
+	// a multiple of 4 because vertex strides and offsets must be aligned
+	// to 4 bytes. Only 8-bit x2 items are rounded up, by two zero bytes.
+	pub fn get_aligned_size(&self, item_size: u32) -> u32 {
+		let size = self.get_padded_item_size(item_size) * self.get_size();
+		(size + 3) / 4 * 4
+	}
+}
+
+pub enum AttributeData {
+	Float16(Vec<u16>),
+	Float32(Vec<f32>),
+	Sint8(Vec<i8>),
+	Sint16(Vec<i16>),
+	Uint8(Vec<u8>),
+	Uint16(Vec<u16>),
+	Uint32(Vec<u32>),
+}
+
+impl AttributeData {
+	pub fn len(&self) -> usize {
+		match self {
+			AttributeData::Float16(data) => data.len(),
+			AttributeData::Float32(data) => data.len(),
+			AttributeData::Sint8(data) => data.len(),
+			AttributeData::Sint16(data) => data.len(),
+			AttributeData::Uint8(data) => data.len(),
+			AttributeData::Uint16(data) => data.len(),
+			AttributeData::Uint32(data) => data.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	pub fn get_type(&self) -> AttributeType {
+		match self {
+			AttributeData::Float16(_) => AttributeType::Float16,
+			AttributeData::Float32(_) => AttributeType::Float32,
+			AttributeData::Sint8(_) => AttributeType::Sint8,
+			AttributeData::Sint16(_) => AttributeType::Sint16,
+			AttributeData::Uint8(_) => AttributeType::Uint8,
+			AttributeData::Uint16(_) => AttributeType::Uint16,
+			AttributeData::Uint32(_) => AttributeType::Uint32,
+		}
+	}
+
+	// In native endian
+	pub fn borrow_bytes(&self) -> &[u8] {
+		match self {
+			AttributeData::Float16(data) => bytemuck::cast_slice(data),
+			AttributeData::Float32(data) => bytemuck::cast_slice(data),
+			AttributeData::Sint8(data) => bytemuck::cast_slice(data),
+			AttributeData::Sint16(data) => bytemuck::cast_slice(data),
+			AttributeData::Uint8(data) => bytemuck::cast_slice(data),
+			AttributeData::Uint16(data) => bytemuck::cast_slice(data),
+			AttributeData::Uint32(data) => bytemuck::cast_slice(data),
+		}
+	}
+}
+
+// Vertex data of a geometry. 8-bit and 16-bit data with item size 1 or 3
+// have no vertex formats. They are uploaded padded with zero components
+// (see get_padded_item_size()), and 8-bit x2 items with two zero bytes
+// (see get_aligned_size()), so the data is copied on every upload and
+// takes more GPU memory than on CPU. Use item size 4, or 2 for 16-bit,
+// to avoid it. Shaders read the padded components as zeros.
 pub struct Attribute {
-	count: u32,
-	data: Vec<f32>,
-	_item_size: u32,
+	data: AttributeData,
+	item_size: u32,
+	// 8-bit and 16-bit integers are read as floats in [0, 1]
+	// (unsigned) or [-1, 1] (signed) in shaders if true
+	normalized: bool,
+	version: u64,
 }
 
 impl Attribute {
 	pub fn new(data: Vec<f32>, item_size: u32) -> Self {
+		Self::new_typed(AttributeData::Float32(data), item_size, false)
+	}
+
+	pub fn new_typed(data: AttributeData, item_size: u32, normalized: bool) -> Self {
 		Attribute {
-			count: data.len() as u32 / item_size,
 			data: data,
-			_item_size: item_size,
+			item_size: item_size,
+			normalized: normalized,
+			version: 0,
 		}
 	}
 
 	pub fn get_count(&self) -> u32 {
-		self.count
+		self.data.len() as u32 / self.item_size
+	}
+
+	pub fn get_item_size(&self) -> u32 {
+		self.item_size
+	}
+
+	pub fn get_type(&self) -> AttributeType {
+		self.data.get_type()
 	}
 
-	pub fn borrow_data(&self) -> &Vec<f32> {
+	// See AttributeType::get_padded_item_size()
+	pub fn get_padded_item_size(&self) -> u32 {
+		self.get_type().get_padded_item_size(self.item_size)
+	}
+
+	// See AttributeType::get_aligned_size()
+	pub fn get_aligned_size(&self) -> u32 {
+		self.get_type().get_aligned_size(self.item_size)
+	}
+
+	pub fn is_normalized(&self) -> bool {
+		self.normalized
+	}
+
+	// Incremented whenever the data can be changed so that
+	// the renderer knows when to re-upload it
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn borrow_data(&self) -> &AttributeData {
 		&self.data
 	}
+
+	pub fn borrow_data_mut(&mut self) -> &mut AttributeData {
+		self.version += 1;
+		&mut self.data
+	}
 }
@@ -1,36 +1,123 @@
-use std::collections::HashMap;
+use std::{
+	collections::HashMap,
+	ops::Range,
+};
 
 use crate::{
 	geometry::{
 		attribute::Attribute,
 		index::Index,
+		interleaved_buffer::InterleavedAttribute,
 	},
 	resource::resource::ResourceId,
 };
 
-// @TODO: Support shared attribute
+// A range of the geometry drawn with a material of the mesh. start and
+// count are in indices, or in vertices if the geometry has no index.
+#[derive(Clone, Copy)]
+pub struct GeometryGroup {
+	count: u32,
+	material_index: usize,
+	start: u32,
+}
+
+impl GeometryGroup {
+	pub fn new(start: u32, count: u32, material_index: usize) -> Self {
+		GeometryGroup {
+			count: count,
+			material_index: material_index,
+			start: start,
+		}
+	}
+
+	pub fn get_start(&self) -> u32 {
+		self.start
+	}
+
+	pub fn get_count(&self) -> u32 {
+		self.count
+	}
+
+	pub fn get_material_index(&self) -> usize {
+		self.material_index
+	}
+}
+
 pub struct Geometry {
-	attributes: HashMap<&'static str, ResourceId<Attribute>>,
+	attributes: HashMap<String, ResourceId<Attribute>>,
+	draw_range: (u32, u32),
+	groups: Vec<GeometryGroup>,
 	index: Option<ResourceId<Index>>,
+	interleaved_attributes: HashMap<String, InterleavedAttribute>,
+	version: u64,
 }
 
 impl Geometry {
 	pub fn new() -> Self {
 		Geometry {
 			attributes: HashMap::new(),
+			draw_range: (0, u32::MAX),
+			groups: Vec::new(),
 			index: None,
+			interleaved_attributes: HashMap::new(),
+			version: 0,
 		}
 	}
 
-	pub fn set_attribute(&mut self, key: &'static str, attribute: ResourceId<Attribute>) -> &mut Self {
-		self.attributes.insert(key, attribute);
+	// Any name can be used. Attributes are passed to the vertex shader
+	// under their names with non-alphanumeric characters replaced with
+	// underscores. position is required to render, and normal and uv
+	// are used by the built-in shader if present. Names starting with
+	// instance_ are reserved for InstancedMesh. Replaces the interleaved
+	// attribute of the same name if any.
+	pub fn set_attribute(&mut self, key: &str, attribute: ResourceId<Attribute>) -> &mut Self {
+		self.interleaved_attributes.remove(key);
+		self.attributes.insert(key.to_string(), attribute);
+		self.version += 1;
 		self
 	}
 
-	pub fn borrow_attribute(&self, key: &'static str) -> Option<&ResourceId<Attribute>> {
+	pub fn borrow_attribute(&self, key: &str) -> Option<&ResourceId<Attribute>> {
 		self.attributes.get(key)
 	}
 
+	pub fn remove_attribute(&mut self, key: &str) -> Option<ResourceId<Attribute>> {
+		self.version += 1;
+		self.attributes.remove(key)
+	}
+
+	pub fn borrow_attributes(&self) -> &HashMap<String, ResourceId<Attribute>> {
+		&self.attributes
+	}
+
+	// Attributes in the same InterleavedBuffer are bound as one vertex
+	// buffer. Replaces the attribute of the same name if any.
+	pub fn set_interleaved_attribute(&mut self, key: &str, attribute: InterleavedAttribute) -> &mut Self {
+		self.attributes.remove(key);
+		self.interleaved_attributes.insert(key.to_string(), attribute);
+		self.version += 1;
+		self
+	}
+
+	pub fn borrow_interleaved_attribute(&self, key: &str) -> Option<&InterleavedAttribute> {
+		self.interleaved_attributes.get(key)
+	}
+
+	pub fn remove_interleaved_attribute(&mut self, key: &str) -> Option<InterleavedAttribute> {
+		self.version += 1;
+		self.interleaved_attributes.remove(key)
+	}
+
+	pub fn borrow_interleaved_attributes(&self) -> &HashMap<String, InterleavedAttribute> {
+		&self.interleaved_attributes
+	}
+
+	// Incremented whenever the attributes can be changed so that
+	// the renderer knows when to rebuild the vertex layout
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
 	pub fn set_index(&mut self, index: ResourceId<Index>) -> &mut Self {
 		self.index = Some(index);
 		self
@@ -44,4 +131,57 @@ impl Geometry {
 	pub fn borrow_index(&self) -> Option<&ResourceId<Index>> {
 		self.index.as_ref()
 	}
+
+	// Limits what is drawn, in indices or in vertices like GeometryGroup.
+	// Groups are clipped by it. count is clamped to the data so
+	// u32::MAX, the default, means to the end.
+	pub fn set_draw_range(&mut self, start: u32, count: u32) -> &mut Self {
+		self.draw_range = (start, count);
+		self
+	}
+
+	// (start, count)
+	pub fn get_draw_range(&self) -> (u32, u32) {
+		self.draw_range
+	}
+
+	// With groups, only the groups are drawn, each with the material
+	// of the mesh at material_index. Groups whose material index
+	// is out of the mesh materials are skipped.
+	pub fn add_group(&mut self, start: u32, count: u32, material_index: usize) -> &mut Self {
+		self.groups.push(GeometryGroup::new(start, count, material_index));
+		self
+	}
+
+	pub fn clear_groups(&mut self) -> &mut Self {
+		self.groups.clear();
+		self
+	}
+
+	pub fn borrow_groups(&self) -> &Vec<GeometryGroup> {
+		&self.groups
+	}
+
+	// Material index and range pairs to draw, clipped by the draw range
+	// and count, the number of indices or vertices. Empty ranges are omitted.
+	pub fn collect_draw_ranges(&self, count: u32) -> Vec<(usize, Range<u32>)> {
+		let (start, range_count) = self.draw_range;
+		let range = clip_range(0..count, start, range_count);
+		match self.groups.is_empty() {
+			true => vec![(0, range)],
+			false => self.groups
+				.iter()
+				.map(|group| (
+					group.material_index,
+					clip_range(range.clone(), group.start, group.count),
+				))
+				.collect(),
+		}.into_iter().filter(|(_, range)| !range.is_empty()).collect()
+	}
+}
+
+fn clip_range(range: Range<u32>, start: u32, count: u32) -> Range<u32> {
+	let end = start.saturating_add(count).min(range.end);
+	let start = start.max(range.start).min(end);
+	start..end
 }
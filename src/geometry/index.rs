@@ -1,23 +1,68 @@
 // @TODO: Should we reuse Attribute?
 
+pub enum IndexFormat {
+	Uint16,
+	Uint32,
+}
+
+pub enum IndexData {
+	Uint16(Vec<u16>),
+	Uint32(Vec<u32>),
+}
+
 pub struct Index {
-	count: u32,
-	data: Vec<u16>,
+	data: IndexData,
+	version: u64,
 }
 
 impl Index {
 	pub fn new(data: Vec<u16>) -> Self {
 		Index {
-			count: data.len() as u32,
-			data: data,
+			data: IndexData::Uint16(data),
+			version: 0,
+		}
+	}
+
+	pub fn new_u32(data: Vec<u32>) -> Self {
+		Index {
+			data: IndexData::Uint32(data),
+			version: 0,
+		}
+	}
+
+	// Stores as 16-bit indices if all the values fit in,
+	// otherwise as 32-bit indices
+	pub fn from_u32(data: Vec<u32>) -> Self {
+		match data.iter().all(|index| *index <= u16::MAX as u32) {
+			true => Self::new(data.iter().map(|index| *index as u16).collect()),
+			false => Self::new_u32(data),
 		}
 	}
 
 	pub fn get_count(&self) -> u32 {
-		self.count
+		match &self.data {
+			IndexData::Uint16(data) => data.len() as u32,
+			IndexData::Uint32(data) => data.len() as u32,
+		}
+	}
+
+	pub fn get_format(&self) -> IndexFormat {
+		match &self.data {
+			IndexData::Uint16(_) => IndexFormat::Uint16,
+			IndexData::Uint32(_) => IndexFormat::Uint32,
+		}
 	}
 
-	pub fn borrow_data(&self) -> &Vec<u16> {
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn borrow_data(&self) -> &IndexData {
 		&self.data
 	}
+
+	pub fn borrow_data_mut(&mut self) -> &mut IndexData {
+		self.version += 1;
+		&mut self.data
+	}
 }
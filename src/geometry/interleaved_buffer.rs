@@ -0,0 +1,105 @@
+use crate::{
+	geometry::attribute::AttributeType,
+	resource::resource::ResourceId,
+};
+
+// Vertex data of multiple attributes in one buffer. A vertex takes
+// stride bytes and InterleavedAttribute tells where an attribute is
+// in it. The stride must be a multiple of 4 to be rendered.
+pub struct InterleavedBuffer {
+	data: Vec<u8>,
+	stride: u32,
+	version: u64,
+}
+
+impl InterleavedBuffer {
+	pub fn new(data: Vec<u8>, stride: u32) -> Self {
+		InterleavedBuffer {
+			data: data,
+			stride: stride,
+			version: 0,
+		}
+	}
+
+	pub fn get_count(&self) -> u32 {
+		match self.stride {
+			0 => 0,
+			stride => self.data.len() as u32 / stride,
+		}
+	}
+
+	// In bytes
+	pub fn get_stride(&self) -> u32 {
+		self.stride
+	}
+
+	// Incremented whenever the data can be changed so that
+	// the renderer knows when to re-upload it
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn borrow_data(&self) -> &Vec<u8> {
+		&self.data
+	}
+
+	pub fn borrow_data_mut(&mut self) -> &mut Vec<u8> {
+		self.version += 1;
+		&mut self.data
+	}
+}
+
+// An attribute in an InterleavedBuffer. Shaders read padded item size
+// components (see AttributeType::get_padded_item_size()) so they must
+// fit in the stride from the offset. The offset must be a multiple of 4.
+#[derive(Clone)]
+pub struct InterleavedAttribute {
+	attribute_type: AttributeType,
+	buffer: ResourceId<InterleavedBuffer>,
+	item_size: u32,
+	normalized: bool,
+	offset: u32,
+}
+
+impl InterleavedAttribute {
+	pub fn new(
+		buffer: ResourceId<InterleavedBuffer>,
+		attribute_type: AttributeType,
+		item_size: u32,
+		offset: u32,
+		normalized: bool,
+	) -> Self {
+		InterleavedAttribute {
+			attribute_type: attribute_type,
+			buffer: buffer,
+			item_size: item_size,
+			normalized: normalized,
+			offset: offset,
+		}
+	}
+
+	pub fn borrow_buffer(&self) -> &ResourceId<InterleavedBuffer> {
+		&self.buffer
+	}
+
+	pub fn get_type(&self) -> AttributeType {
+		self.attribute_type
+	}
+
+	pub fn get_item_size(&self) -> u32 {
+		self.item_size
+	}
+
+	pub fn get_padded_item_size(&self) -> u32 {
+		self.attribute_type.get_padded_item_size(self.item_size)
+	}
+
+	// In bytes from the start of a vertex
+	pub fn get_offset(&self) -> u32 {
+		self.offset
+	}
+
+	pub fn is_normalized(&self) -> bool {
+		self.normalized
+	}
+}
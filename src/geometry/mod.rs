@@ -1,3 +1,5 @@
 pub mod attribute;
 pub mod geometry;
 pub mod index;
+pub mod interleaved_buffer;
+pub mod vertex_layout;
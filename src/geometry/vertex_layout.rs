@@ -0,0 +1,316 @@
+use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
+	geometry::{
+		attribute::{
+			Attribute,
+			AttributeType,
+		},
+		geometry::Geometry,
+		interleaved_buffer::InterleavedBuffer,
+	},
+	resource::resource::ResourcePools,
+};
+
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct VertexAttributeLayout {
+	attribute_type: AttributeType,
+	item_size: u32,
+	name: String,
+	normalized: bool,
+	offset: u32,
+	padded_item_size: u32,
+	variable_name: String,
+}
+
+impl VertexAttributeLayout {
+	pub fn get_attribute_type(&self) -> AttributeType {
+		self.attribute_type
+	}
+
+	pub fn get_item_size(&self) -> u32 {
+		self.item_size
+	}
+
+	// Components per vertex in the vertex buffer and the shader
+	pub fn get_padded_item_size(&self) -> u32 {
+		self.padded_item_size
+	}
+
+	pub fn is_normalized(&self) -> bool {
+		self.normalized
+	}
+
+	// In bytes from the start of a vertex in the vertex buffer
+	pub fn get_offset(&self) -> u32 {
+		self.offset
+	}
+
+	// In bytes
+	pub fn get_padded_size(&self) -> u32 {
+		self.padded_item_size * self.attribute_type.get_size()
+	}
+
+	// True if the shader reads the attribute as signed or unsigned integers
+	pub fn is_integer(&self) -> bool {
+		match self.attribute_type {
+			AttributeType::Float16 |
+			AttributeType::Float32 => false,
+			AttributeType::Uint32 => true,
+			AttributeType::Sint8 |
+			AttributeType::Sint16 |
+			AttributeType::Uint8 |
+			AttributeType::Uint16 => !self.normalized,
+		}
+	}
+
+	pub fn is_signed(&self) -> bool {
+		match self.attribute_type {
+			AttributeType::Sint8 |
+			AttributeType::Sint16 => true,
+			_ => false,
+		}
+	}
+
+	pub fn borrow_name(&self) -> &String {
+		&self.name
+	}
+
+	// Name of the vertex shader input, unique in the layout. Prefixed so
+	// that names starting with digits or equal to WGSL keywords are valid.
+	pub fn borrow_variable_name(&self) -> &String {
+		&self.variable_name
+	}
+}
+
+// a_ and the name with the characters invalid in WGSL identifiers
+// replaced with _, suffixed with a number if already used
+fn create_variable_name(name: &str, attributes: &[VertexAttributeLayout]) -> String {
+	let base = "a_".to_string() + &name
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect::<String>();
+	let mut variable_name = base.clone();
+	let mut count = 0;
+	while attributes.iter().any(|attribute| attribute.variable_name == variable_name) {
+		count += 1;
+		variable_name = format!("{}_{}", base, count);
+	}
+	variable_name
+}
+
+// A vertex buffer slot, for a separate Attribute, an InterleavedBuffer
+// or instance data
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct VertexBufferLayout {
+	// Indices in VertexLayout attributes
+	attributes: Vec<usize>,
+	// Advances per instance rather than per vertex if true
+	instanced: bool,
+	stride: u32,
+}
+
+impl VertexBufferLayout {
+	pub fn borrow_attributes(&self) -> &Vec<usize> {
+		&self.attributes
+	}
+
+	pub fn is_instanced(&self) -> bool {
+		self.instanced
+	}
+
+	// In bytes
+	pub fn get_stride(&self) -> u32 {
+		self.stride
+	}
+}
+
+// Vertex inputs of a geometry. Attributes are sorted by name, and the index
+// of an attribute is its shader location. Vertex buffers are ordered by
+// the first location of them. Instance inputs follow them if added.
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct VertexLayout {
+	attributes: Vec<VertexAttributeLayout>,
+	buffers: Vec<VertexBufferLayout>,
+}
+
+impl VertexLayout {
+	// Attributes not in the pools, with item size over 4, interleaved ones
+	// not fitting in the stride or misaligned, or whose names start with
+	// instance_, reserved for the instance inputs, are skipped
+	pub fn from_geometry(geometry: &Geometry, pools: &ResourcePools) -> Self {
+		let attribute_pool = pools.borrow::<Attribute>();
+		let interleaved_buffer_pool = pools.borrow::<InterleavedBuffer>();
+
+		// With the vertex buffer key, None for separate attributes
+		let mut attributes = Vec::new();
+
+		for (name, rid) in geometry.borrow_attributes().iter() {
+			if name.starts_with("instance_") {
+				continue;
+			}
+			if let Some(attribute) = attribute_pool.borrow(rid) {
+				if attribute.get_item_size() >= 1 && attribute.get_item_size() <= 4 {
+					attributes.push((VertexAttributeLayout {
+						attribute_type: attribute.get_type(),
+						item_size: attribute.get_item_size(),
+						name: name.clone(),
+						normalized: attribute.is_normalized(),
+						offset: 0,
+						padded_item_size: attribute.get_padded_item_size(),
+						variable_name: String::new(),
+					}, None));
+				}
+			}
+		}
+
+		for (name, attribute) in geometry.borrow_interleaved_attributes().iter() {
+			if name.starts_with("instance_") {
+				continue;
+			}
+			if let Some(buffer) = interleaved_buffer_pool.borrow(attribute.borrow_buffer()) {
+				let layout = VertexAttributeLayout {
+					attribute_type: attribute.get_type(),
+					item_size: attribute.get_item_size(),
+					name: name.clone(),
+					normalized: attribute.is_normalized(),
+					offset: attribute.get_offset(),
+					padded_item_size: attribute.get_padded_item_size(),
+					variable_name: String::new(),
+				};
+				if layout.item_size >= 1 && layout.item_size <= 4 &&
+					layout.offset % 4 == 0 && buffer.get_stride() % 4 == 0 &&
+					layout.offset + layout.get_padded_size() <= buffer.get_stride() {
+					attributes.push((layout, Some((*attribute.borrow_buffer(), buffer.get_stride()))));
+				}
+			}
+		}
+
+		attributes.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+		// Separate attributes have a vertex buffer each
+		let mut buffers: Vec<VertexBufferLayout> = Vec::new();
+		let mut buffer_keys = Vec::new();
+		for (location, (attribute, key)) in attributes.iter().enumerate() {
+			let slot = match key {
+				Some((rid, _)) => buffer_keys.iter().position(|k| k == &Some(*rid)),
+				None => None,
+			};
+			match slot {
+				Some(slot) => buffers[slot].attributes.push(location),
+				None => {
+					buffer_keys.push(key.map(|(rid, _)| rid));
+					buffers.push(VertexBufferLayout {
+						attributes: vec![location],
+						instanced: false,
+						stride: match key {
+							Some((_, stride)) => *stride,
+							None => attribute.attribute_type.get_aligned_size(attribute.item_size),
+						},
+					});
+				},
+			};
+		}
+
+		let mut layout = VertexLayout {
+			attributes: Vec::new(),
+			buffers: buffers,
+		};
+		for (mut attribute, _) in attributes.into_iter() {
+			attribute.variable_name = create_variable_name(&attribute.name, &layout.attributes);
+			layout.attributes.push(attribute);
+		}
+		layout
+	}
+
+	// Adds a vertex buffer advancing per instance with instance_matrix0-3,
+	// the columns of the instance matrix, and instance_color if color is true,
+	// in f32. See InstancedMesh. Call fit_limits() after this so that
+	// they are in the limits with the geometry inputs.
+	pub fn add_instance_attributes(&mut self, color: bool) -> &mut Self {
+		let mut inputs = (0..4)
+			.map(|column| (format!("instance_matrix{}", column), 4))
+			.collect::<Vec<_>>();
+		if color {
+			inputs.push(("instance_color".to_string(), 3));
+		}
+
+		let mut locations = Vec::new();
+		let mut offset = 0;
+		for (name, item_size) in inputs.into_iter() {
+			locations.push(self.attributes.len());
+			self.attributes.push(VertexAttributeLayout {
+				attribute_type: AttributeType::Float32,
+				item_size: item_size,
+				normalized: false,
+				offset: offset,
+				padded_item_size: item_size,
+				variable_name: create_variable_name(&name, &self.attributes),
+				name: name,
+			});
+			offset += item_size * 4;
+		}
+
+		self.buffers.push(VertexBufferLayout {
+			attributes: locations,
+			instanced: true,
+			stride: offset,
+		});
+		self
+	}
+
+	// Drops attributes from the last location until the layout fits in
+	// the device limits. Position, normal, uv and the instance inputs the
+	// shaders read are kept. Fails if they don't fit by themselves.
+	pub fn fit_limits(&mut self, max_buffers: u32, max_attributes: u32) -> Result<()> {
+		while self.buffers.len() > max_buffers as usize ||
+			self.attributes.len() > max_attributes as usize {
+			let location = match self.attributes.iter().rposition(|attribute| {
+				match attribute.name.as_str() {
+					"normal" | "position" | "uv" => false,
+					name => !name.starts_with("instance_"),
+				}
+			}) {
+				Some(location) => location,
+				None => return Err(Error::Unsupported(format!(
+					"{} vertex buffers and {} vertex attributes over the limits {} and {}",
+					self.buffers.len(),
+					self.attributes.len(),
+					max_buffers,
+					max_attributes,
+				))),
+			};
+			self.remove(location);
+		}
+		Ok(())
+	}
+
+	// The locations after it are shifted down. Removes its
+	// vertex buffer if no other attribute is in it.
+	fn remove(&mut self, location: usize) {
+		self.attributes.remove(location);
+		for buffer in self.buffers.iter_mut() {
+			buffer.attributes.retain(|l| *l != location);
+			for l in buffer.attributes.iter_mut() {
+				if *l > location {
+					*l -= 1;
+				}
+			}
+		}
+		self.buffers.retain(|buffer| !buffer.attributes.is_empty());
+	}
+
+	pub fn borrow_attributes(&self) -> &Vec<VertexAttributeLayout> {
+		&self.attributes
+	}
+
+	pub fn borrow_buffers(&self) -> &Vec<VertexBufferLayout> {
+		&self.buffers
+	}
+
+	pub fn find(&self, name: &str) -> Option<&VertexAttributeLayout> {
+		self.attributes.iter().find(|attribute| attribute.name == name)
+	}
+}
@@ -1,3 +1,4 @@
+pub mod error;
 pub mod geometry;
 pub mod material;
 pub mod math;
@@ -1,8 +1,15 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
+	geometry::vertex_layout::{
+		VertexAttributeLayout,
+		VertexLayout,
+	},
+	math::color::ColorSpace,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
+		borrow_node,
 	},
 	resource::resource::{
 		ResourceId,
@@ -14,6 +21,7 @@ use crate::{
 	},
 };
 
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub enum Side {
 	BackSide,
 	DoubleSide,
@@ -31,6 +39,7 @@ const PREFIX_CHUNK1: &str = "struct VertexOutput {
   [[location(1)]] normal: vec3<f32>;
   [[location(2)]] uv: vec2<f32>;
   [[location(3)]] view_position: vec3<f32>;
+  [[location(4)]] color: vec3<f32>;
 };
 
 [[block]]
@@ -101,18 +110,13 @@ fn linear_to_srgb(value: vec4<f32>) -> vec4<f32> {
 }
 ";
 
-const VERTEX_CHUNK: &str = "
+const VERTEX_CHUNK1: &str = "
 [[stage(vertex)]]
-fn vs_main(
-  [[location(0)]] position: vec3<f32>,
-  [[location(1)]] normal: vec3<f32>,
-  [[location(2)]] uv: vec2<f32>,
-) -> VertexOutput {
+fn vs_main(in: VertexInput) -> VertexOutput {
   var out: VertexOutput;
-  let mv_position = object.model_view_matrix * vec4<f32>(position, 1.0);
-  out.position = camera.projection_matrix * mv_position;
-  out.normal = normalize(object.normal_matrix * normal);
-  out.uv = uv;
+";
+
+const VERTEX_CHUNK2: &str = "  out.position = camera.projection_matrix * mv_position;
   out.view_position = -mv_position.xyz;
   return out;
 }
@@ -133,15 +137,12 @@ const FRAGMENT_CHUNK2: &str = "
     let light_factor = clamp(dot(normalize(in.normal), light_dir), 0.0, 1.0) * light_color;
     color = color * light_factor.rgb;
   }
-  // @TODO: Use material node?
-  // @TODO: Color management
-  return linear_to_srgb(vec4<f32>(color, alpha));
-}
 ";
 
 pub struct Material {
 	color: ResourceId<Box<dyn MaterialNode>>,
 	side: Side,
+	version: u64,
 }
 
 impl Material {
@@ -152,6 +153,7 @@ impl Material {
 		Material {
 			color: color,
 			side: side,
+			version: 0,
 		}
 	}
 
@@ -159,46 +161,70 @@ impl Material {
 		&self.side
 	}
 
+	// Incremented when the shader or the render state can be changed
+	// so that the renderer knows when to rebuild the pipeline.
+	// Uniform values in the nodes are uploaded every frame.
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn borrow_color(&self) -> &ResourceId<Box<dyn MaterialNode>> {
+		&self.color
+	}
+
+	pub fn set_color(&mut self, color: ResourceId<Box<dyn MaterialNode>>) -> &mut Self {
+		self.color = color;
+		self.version += 1;
+		self
+	}
+
+	pub fn set_side(&mut self, side: Side) -> &mut Self {
+		self.side = side;
+		self.version += 1;
+		self
+	}
+
+	// Error if any node the material refers to has been removed
 	// @TODO: Optimize?
 	fn borrow_nodes(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> Vec<ResourceId<Box<dyn MaterialNode>>> {
+	) -> Result<Vec<ResourceId<Box<dyn MaterialNode>>>> {
 		let mut nodes = Vec::new();
 		let mut visited = HashMap::new();
-		pool.borrow(&self.color).unwrap().collect_nodes(
+		borrow_node(pool, &self.color)?.collect_nodes(
 			pool,
 			&mut nodes,
 			&mut visited,
 			self.color,
-		);
-		nodes
+		)?;
+		Ok(nodes)
 	}
 
 	// @TODO: Optimize?
 	pub fn borrow_contents<'a>(
 		&self,
 		pool: &'a ResourcePool<Box<dyn MaterialNode>>,
-	) -> Vec<&'a UniformContents> {
+	) -> Result<Vec<&'a UniformContents>> {
 		let mut contents = Vec::<>::new();
-		for node in self.borrow_nodes(pool).iter() {
-			let node = pool.borrow(node).unwrap();
+		for node in self.borrow_nodes(pool)?.iter() {
+			let node = borrow_node(pool, node)?;
 			if let Some(c) = node.borrow_contents() {
 				contents.push(c);
 			}
 		}
-		contents
+		Ok(contents)
 	}
 
 	// @TODO: Optimize?
 	pub fn borrow_textures<'a>(
 		&'a self,
 		pool: &'a ResourcePool<Box<dyn MaterialNode>>,
-	) -> Vec<&'a ResourceId<Texture>> {
+	) -> Result<Vec<&'a ResourceId<Texture>>> {
 		let mut textures = Vec::new();
 		let mut founds = HashMap::new();
 
-		for contents in self.borrow_contents(pool).iter() {
+		for contents in self.borrow_contents(pool)?.iter() {
 			match contents {
 				UniformContents::Texture {texture, ..} => {
 					if !founds.contains_key(texture) {
@@ -209,18 +235,18 @@ impl Material {
 				_ => {},
 			};
 		}
-		textures
+		Ok(textures)
 	}
 
 	// @TODO: Optimize?
 	pub fn borrow_samplers<'a>(
 		&'a self,
 		pool: &'a ResourcePool<Box<dyn MaterialNode>>,
-	) -> Vec<&'a ResourceId<Sampler>> {
+	) -> Result<Vec<&'a ResourceId<Sampler>>> {
 		let mut samplers = Vec::new();
 		let mut founds = HashMap::new();
 
-		for contents in self.borrow_contents(pool).iter() {
+		for contents in self.borrow_contents(pool)?.iter() {
 			match contents {
 				UniformContents::Texture {sampler, ..} => {
 					if !founds.contains_key(sampler) {
@@ -231,56 +257,124 @@ impl Material {
 				_ => {},
 			};
 		}
-		samplers
+		Ok(samplers)
 	}
 
+	// output_color_space is the encoding the fragment shader outputs in.
+	// It should be Linear if the render target encodes to sRGB on write.
+	// vertex_layout is the one of the geometry to be drawn with.
 	pub fn build_shader_code(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> String {
-		self.build_prefix(pool) +
-		&self.build_vertex_shader() +
-		&self.build_fragment_shader(pool)
+		output_color_space: &ColorSpace,
+		vertex_layout: &VertexLayout,
+	) -> Result<String> {
+		Ok(self.build_prefix(pool)? +
+		&self.build_vertex_shader(vertex_layout) +
+		&self.build_fragment_shader(pool, output_color_space)?)
 	}
 
 	fn build_prefix(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> String {
-		PREFIX_CHUNK1.to_string() +
-		&self.build_uniform_block_declaration(pool) +
+	) -> Result<String> {
+		Ok(PREFIX_CHUNK1.to_string() +
+		&self.build_uniform_block_declaration(pool)? +
 		PREFIX_CHUNK2 +
-		&self.build_texture_declaration(pool) +
+		&self.build_texture_declaration(pool)? +
 		PREFIX_CHUNK3 +
-		&self.build_functions(pool)
+		&self.build_functions(pool)?)
 	}
 
-	fn build_vertex_shader(&self) -> String {
-		VERTEX_CHUNK.to_string()
+	// Missing normal and uv are filled with constants. Instance matrix
+	// and color are applied if the layout has the instance inputs.
+	fn build_vertex_shader(&self, vertex_layout: &VertexLayout) -> String {
+		let mut s = "\nstruct VertexInput {\n".to_string();
+		for (location, attribute) in vertex_layout.borrow_attributes().iter().enumerate() {
+			s += &format!("  [[location({})]] {}: {};\n",
+				location,
+				attribute.borrow_variable_name(),
+				get_wgsl_type(attribute),
+			);
+		}
+		s += "};\n";
+
+		let instanced = vertex_layout.find("instance_matrix0").is_some();
+
+		let position = match vertex_layout.find("position") {
+			Some(attribute) => to_vec3(attribute),
+			None => "vec3<f32>(0.0)".to_string(),
+		};
+		let position = match instanced {
+			true => format!("instance_matrix * vec4<f32>({}, 1.0)", position),
+			false => format!("vec4<f32>({}, 1.0)", position),
+		};
+		let normal = match (vertex_layout.find("normal"), instanced) {
+			(Some(attribute), true) => format!(
+				"normalize(object.normal_matrix * mat3x3<f32>(instance_matrix[0].xyz, instance_matrix[1].xyz, instance_matrix[2].xyz) * {})",
+				to_vec3(attribute),
+			),
+			(Some(attribute), false) => format!("normalize(object.normal_matrix * {})", to_vec3(attribute)),
+			(None, _) => "vec3<f32>(0.0, 0.0, 1.0)".to_string(),
+		};
+		let uv = match vertex_layout.find("uv") {
+			Some(attribute) => to_vec2(attribute),
+			None => "vec2<f32>(0.0)".to_string(),
+		};
+		let color = match vertex_layout.find("instance_color") {
+			Some(attribute) => format!("in.{}", attribute.borrow_variable_name()),
+			None => "vec3<f32>(1.0)".to_string(),
+		};
+
+		s +
+		VERTEX_CHUNK1 +
+		&match instanced {
+			true => format!(
+				"  let instance_matrix = mat4x4<f32>({});\n",
+				(0..4)
+					.filter_map(|column| vertex_layout.find(&format!("instance_matrix{}", column)))
+					.map(|attribute| format!("in.{}", attribute.borrow_variable_name()))
+					.collect::<Vec<_>>()
+					.join(", "),
+			),
+			false => String::new(),
+		} +
+		&format!("  let mv_position = object.model_view_matrix * {};\n", position) +
+		&format!("  out.normal = {};\n", normal) +
+		&format!("  out.uv = {};\n", uv) +
+		&format!("  out.color = {};\n", color) +
+		VERTEX_CHUNK2
 	}
 
 	fn build_fragment_shader(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> String {
+		output_color_space: &ColorSpace,
+	) -> Result<String> {
 		let mut visited = HashMap::new();
-		let color = pool.borrow(&self.color).unwrap();
+		let color = borrow_node(pool, &self.color)?;
 
-		FRAGMENT_CHUNK1.to_string() +
-		&color.build_fragment_shader(pool, &mut visited, self.color.id) +
+		Ok(FRAGMENT_CHUNK1.to_string() +
+		&color.build_fragment_shader(pool, &mut visited, self.color.id)? +
 		&format!("var color: vec3<f32> = {};\n", color.get_fragment_output(self.color.id)) +
-		&FRAGMENT_CHUNK2.to_string()
+		"  color = color * in.color;\n" +
+		&FRAGMENT_CHUNK2.to_string() +
+		// @TODO: Use material node?
+		match output_color_space {
+			ColorSpace::Linear => "  return vec4<f32>(color, alpha);\n}\n",
+			ColorSpace::SRGB => "  return linear_to_srgb(vec4<f32>(color, alpha));\n}\n",
+		})
 	}
 
 	// @TODO: Optimize?
 	fn build_uniform_block_declaration(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> String {
+	) -> Result<String> {
 		// bindings for textures start with 3
 		let mut s = "".to_string();
-		for node_id in self.borrow_nodes(pool).iter() {
-			let node = pool.borrow(node_id).unwrap();
+		for node_id in self.borrow_nodes(pool)?.iter() {
+			let node = borrow_node(pool, node_id)?;
 			if let Some(contents) = node.borrow_contents() {
 				match contents {
 					UniformContents::Texture {..} => {},
@@ -290,42 +384,84 @@ impl Material {
 				}
 			}
 		}
-		s
+		Ok(s)
 	}
 
 	// @TODO: Optimize?
 	fn build_texture_declaration(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> String {
+	) -> Result<String> {
 		// bindings for textures start with 3
 		let mut binding = 3;
 		let mut s = "".to_string();
 
 		// Textures first
-		for texture in self.borrow_textures(pool).iter() {
+		for texture in self.borrow_textures(pool)?.iter() {
 			s += &format!("\n[[group(0), binding({})]]\n", binding);
 			s += &format!("var texture_{}: texture_2d<f32>;\n", texture.id);
 			binding += 1;
 		}
 
 		// Samplers next
-		for sampler in self.borrow_samplers(pool).iter() {
+		for sampler in self.borrow_samplers(pool)?.iter() {
 			s += &format!("\n[[group(0), binding({})]]\n", binding);
 			s += &format!("var sampler_{}: sampler;\n", sampler.id);
 			binding += 1;
 		}
-		s
+		Ok(s)
 	}
 
 	fn build_functions(
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
-	) -> String {
+	) -> Result<String> {
 		let mut s = "".to_string();
-		for node_id in self.borrow_nodes(pool).iter() {
-			s += &pool.borrow(node_id).unwrap().build_functions(node_id.id);
+		for node_id in self.borrow_nodes(pool)?.iter() {
+			s += &borrow_node(pool, node_id)?.build_functions(node_id.id);
 		}
-		s
+		Ok(s)
+	}
+}
+
+fn get_wgsl_type(attribute: &VertexAttributeLayout) -> String {
+	let scalar = match (attribute.is_integer(), attribute.is_signed()) {
+		(false, _) => "f32",
+		(true, false) => "u32",
+		(true, true) => "i32",
+	};
+	match attribute.get_padded_item_size() {
+		1 => scalar.to_string(),
+		size => format!("vec{}<{}>", size, scalar),
+	}
+}
+
+// The attribute as f32 or vector of f32 of the padded item size.
+// Padding components are zeros.
+fn to_float(attribute: &VertexAttributeLayout) -> String {
+	let name = format!("in.{}", attribute.borrow_variable_name());
+	match (attribute.is_integer(), attribute.get_padded_item_size()) {
+		(false, _) => name,
+		(true, 1) => format!("f32({})", name),
+		(true, size) => format!("vec{}<f32>({})", size, name),
+	}
+}
+
+fn to_vec2(attribute: &VertexAttributeLayout) -> String {
+	let value = to_float(attribute);
+	match attribute.get_padded_item_size() {
+		1 => format!("vec2<f32>({}, 0.0)", value),
+		2 => value,
+		_ => format!("({}).xy", value),
+	}
+}
+
+fn to_vec3(attribute: &VertexAttributeLayout) -> String {
+	let value = to_float(attribute);
+	match attribute.get_padded_item_size() {
+		1 => format!("vec3<f32>({}, 0.0, 0.0)", value),
+		2 => format!("vec3<f32>({}, 0.0)", value),
+		3 => value,
+		_ => format!("({}).xyz", value),
 	}
 }
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
+		borrow_node,
 	},
 	resource::resource::{
 		ResourceId,
@@ -34,17 +36,18 @@ impl MaterialNode for AddNode {
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	) {
-		pool.borrow(&self.value1).unwrap().collect_nodes(
+	) -> Result<()> {
+		borrow_node(pool, &self.value1)?.collect_nodes(
 			pool, nodes, visited, self.value1,
-		);
-		pool.borrow(&self.value2).unwrap().collect_nodes(
+		)?;
+		borrow_node(pool, &self.value2)?.collect_nodes(
 			pool, nodes, visited, self.value2,
-		);
+		)?;
 		if !visited.contains_key(&self_rid) {
 			visited.insert(self_rid, true);
 			nodes.push(self_rid);
 		}
+		Ok(())
 	}
 
 	fn borrow_contents(&self) -> Option<&UniformContents> {
@@ -64,22 +67,22 @@ impl MaterialNode for AddNode {
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
 		visited: &mut HashMap<usize, bool>,
 		self_id: usize,
-	) -> String {
+	) -> Result<String> {
 		if visited.contains_key(&self_id) {
-			return "".to_string();
+			return Ok("".to_string());
 		}
 		visited.insert(self_id, true);
 
-		let value1 = pool.borrow(&self.value1).unwrap();
-		let value2 = pool.borrow(&self.value2).unwrap();
+		let value1 = borrow_node(pool, &self.value1)?;
+		let value2 = borrow_node(pool, &self.value2)?;
 
-		value1.build_fragment_shader(pool, visited, self.value1.id) +
-		&value2.build_fragment_shader(pool, visited, self.value2.id) +
+		Ok(value1.build_fragment_shader(pool, visited, self.value1.id)? +
+		&value2.build_fragment_shader(pool, visited, self.value2.id)? +
 		&format!("let {} = {} + {};\n",
 			self.get_fragment_output(self_id),
 			value1.get_fragment_output(self.value1.id),
 			value2.get_fragment_output(self.value2.id),
-		)
+		))
 	}
 
 	fn get_fragment_output(&self, self_id: usize) -> String {
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
+		borrow_node,
 	},
 	resource::resource::{
 		ResourceId,
@@ -82,23 +84,24 @@ impl MaterialNode for BRDFNode {
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	) {
-		pool.borrow(&self.desc.base_color).unwrap().collect_nodes(
+	) -> Result<()> {
+		borrow_node(pool, &self.desc.base_color)?.collect_nodes(
 			pool, nodes, visited, self.desc.base_color,
-		);
-		pool.borrow(&self.desc.metallic).unwrap().collect_nodes(
+		)?;
+		borrow_node(pool, &self.desc.metallic)?.collect_nodes(
 			pool, nodes, visited, self.desc.metallic,
-		);
-		pool.borrow(&self.desc.normal).unwrap().collect_nodes(
+		)?;
+		borrow_node(pool, &self.desc.normal)?.collect_nodes(
 			pool, nodes, visited, self.desc.normal,
-		);
-		pool.borrow(&self.desc.roughness).unwrap().collect_nodes(
+		)?;
+		borrow_node(pool, &self.desc.roughness)?.collect_nodes(
 			pool, nodes, visited, self.desc.roughness,
-		);
+		)?;
 		if !visited.contains_key(&self_rid) {
 			visited.insert(self_rid, true);
 			nodes.push(self_rid);
 		}
+		Ok(())
 	}
 
 	fn borrow_contents(&self) -> Option<&UniformContents> {
@@ -119,21 +122,21 @@ impl MaterialNode for BRDFNode {
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
 		visited: &mut HashMap<usize, bool>,
 		self_id: usize,
-	) -> String {
+	) -> Result<String> {
 		if visited.contains_key(&self_id) {
-			return "".to_string();
+			return Ok("".to_string());
 		}
 		visited.insert(self_id, true);
 
-		let base_color = pool.borrow(&self.desc.base_color).unwrap();
-		let metallic = pool.borrow(&self.desc.metallic).unwrap();
-		let normal = pool.borrow(&self.desc.normal).unwrap();
-		let roughness = pool.borrow(&self.desc.roughness).unwrap();
+		let base_color = borrow_node(pool, &self.desc.base_color)?;
+		let metallic = borrow_node(pool, &self.desc.metallic)?;
+		let normal = borrow_node(pool, &self.desc.normal)?;
+		let roughness = borrow_node(pool, &self.desc.roughness)?;
 
-		base_color.build_fragment_shader(pool, visited, self.desc.base_color.id) +
-		&metallic.build_fragment_shader(pool, visited, self.desc.metallic.id) +
-		&normal.build_fragment_shader(pool, visited, self.desc.normal.id) +
-		&roughness.build_fragment_shader(pool, visited, self.desc.roughness.id) +
+		Ok(base_color.build_fragment_shader(pool, visited, self.desc.base_color.id)? +
+		&metallic.build_fragment_shader(pool, visited, self.desc.metallic.id)? +
+		&normal.build_fragment_shader(pool, visited, self.desc.normal.id)? +
+		&roughness.build_fragment_shader(pool, visited, self.desc.roughness.id)? +
 		&format!("let brdf_v_{} = normalize(in.view_position);\n", self_id) +
 		&format!("let brdf_l_{} = normalize(light_dir);\n", self_id) +
 		&format!("let brdf_n_{} = normalize({});\n",
@@ -152,7 +155,7 @@ impl MaterialNode for BRDFNode {
 			roughness.get_fragment_output(self.desc.roughness.id)
 		) +
 		// @TODO: Fix me
-		&format!("use_directional_light = false;\n")
+		&format!("use_directional_light = false;\n"))
 	}
 
 	fn get_fragment_output(&self, self_id: usize) -> String {
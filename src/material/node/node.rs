@@ -1,5 +1,9 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	resource::resource::{
 		ResourceId,
 		ResourcePool,
@@ -20,14 +24,14 @@ pub enum UniformContents {
 	},
 }
 
-pub trait MaterialNode {
+pub trait MaterialNode: Send + Sync {
 	fn collect_nodes (
 		&self,
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	);
+	) -> Result<()>;
 	fn borrow_contents(&self) -> Option<&UniformContents>;
 	fn build_declaration(&self, self_id: usize) -> String;
 	fn build_functions(&self, self_id: usize) -> String;
@@ -36,8 +40,16 @@ pub trait MaterialNode {
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
 		visited: &mut HashMap<usize, bool>,
 		self_id: usize,
-	) -> String;
+	) -> Result<String>;
 	fn get_fragment_output(&self, self_id: usize) -> String;
 }
 
+// Nodes can be removed while other nodes or materials still refer to them
+pub fn borrow_node<'a>(
+	pool: &'a ResourcePool<Box<dyn MaterialNode>>,
+	rid: &ResourceId<Box<dyn MaterialNode>>,
+) -> Result<&'a Box<dyn MaterialNode>> {
+	pool.borrow(rid).ok_or(Error::ResourceNotFound("MaterialNode"))
+}
+
 // @TODO: Ensure unique variable names
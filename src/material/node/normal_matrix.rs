@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
@@ -27,11 +28,12 @@ impl MaterialNode for NormalMatrixNode {
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	) {
+	) -> Result<()> {
 		if !visited.contains_key(&self_rid) {
 			visited.insert(self_rid, true);
 			nodes.push(self_rid);
 		}
+		Ok(())
 	}
 
 	fn borrow_contents(&self) -> Option<&UniformContents> {
@@ -51,8 +53,8 @@ impl MaterialNode for NormalMatrixNode {
 		_pool: &ResourcePool<Box<dyn MaterialNode>>,
 		_visited: &mut HashMap<usize, bool>,
 		_self_id: usize,
-	) -> String {
-		format!("")
+	) -> Result<String> {
+		Ok(format!(""))
 	}
 
 	fn get_fragment_output(&self, _self_id: usize) -> String {
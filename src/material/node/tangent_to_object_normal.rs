@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
+		borrow_node,
 	},
 	resource::resource::{
 		ResourceId,
@@ -10,17 +12,18 @@ use crate::{
 	},
 };
 
+// Derivatives are taken by the caller in the fragment entry point.
+// Functions are emitted to every stage on some backends (e.g. GL),
+// where derivatives in the vertex stage fail to compile.
 const FUNCTION_CHUNK: &str = "
 fn perturb_normal_to_arb(
-  eye_pos: vec3<f32>,
+  q0: vec3<f32>,
+  q1: vec3<f32>,
+  st0: vec2<f32>,
+  st1: vec2<f32>,
   surf_norm: vec3<f32>,
-  map_n: vec3<f32>,
-  uv: vec2<f32>
+  map_n: vec3<f32>
 ) -> vec3<f32> {
-  let q0: vec3<f32> = vec3<f32>(dpdx(eye_pos.x), dpdx(eye_pos.y), dpdx(eye_pos.z));
-  let q1: vec3<f32> = vec3<f32>(dpdy(eye_pos.x), dpdy(eye_pos.y), dpdy(eye_pos.z));
-  let st0: vec2<f32> = dpdx(uv);
-  let st1: vec2<f32> = dpdy(uv);
   let n: vec3<f32> = surf_norm; // normalized
   let q1perp: vec3<f32> = cross(q1, n);
   let q0perp: vec3<f32> = cross(n, q0);
@@ -58,14 +61,15 @@ impl MaterialNode for TangentToObjectNormalNode {
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	) {
-		pool.borrow(&self.node).unwrap().collect_nodes(
+	) -> Result<()> {
+		borrow_node(pool, &self.node)?.collect_nodes(
 			pool, nodes, visited, self.node,
-		);
+		)?;
 		if !visited.contains_key(&self_rid) {
 			visited.insert(self_rid, true);
 			nodes.push(self_rid);
 		}
+		Ok(())
 	}
 
 	fn borrow_contents(&self) -> Option<&UniformContents> {
@@ -86,19 +90,28 @@ impl MaterialNode for TangentToObjectNormalNode {
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
 		visited: &mut HashMap<usize, bool>,
 		self_id: usize,
-	) -> String {
+	) -> Result<String> {
 		if visited.contains_key(&self_id) {
-			return "".to_string();
+			return Ok("".to_string());
 		}
 		visited.insert(self_id, true);
 
-		let node = pool.borrow(&self.node).unwrap();
+		let node = borrow_node(pool, &self.node)?;
 
-		node.build_fragment_shader(pool, visited, self.node.id) +
-		&format!("let {} = perturb_normal_to_arb(-in.view_position, in.normal, {}, in.uv);\n",
+		Ok(node.build_fragment_shader(pool, visited, self.node.id)? +
+		&format!(concat!(
+			"let {0} = perturb_normal_to_arb(\n",
+			"  vec3<f32>(dpdx(-in.view_position.x), dpdx(-in.view_position.y), dpdx(-in.view_position.z)),\n",
+			"  vec3<f32>(dpdy(-in.view_position.x), dpdy(-in.view_position.y), dpdy(-in.view_position.z)),\n",
+			"  dpdx(in.uv),\n",
+			"  dpdy(in.uv),\n",
+			"  in.normal,\n",
+			"  {1}\n",
+			");\n",
+		),
 			self.get_fragment_output(self_id),
 			node.get_fragment_output(self.node.id),
-		)
+		))
 	}
 
 	fn get_fragment_output(&self, self_id: usize) -> String {
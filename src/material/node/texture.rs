@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
@@ -57,11 +58,12 @@ impl MaterialNode for TextureNode {
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	) {
+	) -> Result<()> {
 		if !visited.contains_key(&self_rid) {
 			visited.insert(self_rid, true);
 			nodes.push(self_rid);
 		}
+		Ok(())
 	}
 
 	fn borrow_contents(&self) -> Option<&UniformContents> {
@@ -81,17 +83,17 @@ impl MaterialNode for TextureNode {
 		_pool: &ResourcePool<Box<dyn MaterialNode>>,
 		visited: &mut HashMap<usize, bool>,
 		self_id: usize,
-	) -> String {
+	) -> Result<String> {
 		if visited.contains_key(&self_id) {
-			return "".to_string();
+			return Ok("".to_string());
 		}
 		visited.insert(self_id, true);
 
-		format!("let {} = textureSample({}, {}, in.uv);\n",
+		Ok(format!("let {} = textureSample({}, {}, in.uv);\n",
 			self.get_fragment_output(self_id),
 			self.get_texture_name(),
 			self.get_sampler_name(),
-		)
+		))
 	}
 
 	fn get_fragment_output(&self, self_id: usize) -> String {
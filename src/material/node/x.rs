@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::node::node::{
 		MaterialNode,
 		UniformContents,
+		borrow_node,
 	},
 	resource::resource::{
 		ResourceId,
@@ -31,14 +33,15 @@ impl MaterialNode for XNode {
 		nodes: &mut Vec<ResourceId<Box<dyn MaterialNode>>>,
 		visited: &mut HashMap<ResourceId<Box<dyn MaterialNode>>, bool>,
 		self_rid: ResourceId<Box<dyn MaterialNode>>,
-	) {
-		pool.borrow(&self.node).unwrap().collect_nodes(
+	) -> Result<()> {
+		borrow_node(pool, &self.node)?.collect_nodes(
 			pool, nodes, visited, self.node,
-		);
+		)?;
 		if !visited.contains_key(&self_rid) {
 			visited.insert(self_rid, true);
 			nodes.push(self_rid);
 		}
+		Ok(())
 	}
 
 	fn borrow_contents(&self) -> Option<&UniformContents> {
@@ -58,19 +61,19 @@ impl MaterialNode for XNode {
 		pool: &ResourcePool<Box<dyn MaterialNode>>,
 		visited: &mut HashMap<usize, bool>,
 		self_id: usize,
-	) -> String {
+	) -> Result<String> {
 		if visited.contains_key(&self_id) {
-			return "".to_string();
+			return Ok("".to_string());
 		}
 		visited.insert(self_id, true);
 
-		let node = pool.borrow(&self.node).unwrap();
+		let node = borrow_node(pool, &self.node)?;
 
-		node.build_fragment_shader(pool, visited, self.node.id) +
+		Ok(node.build_fragment_shader(pool, visited, self.node.id)? +
 		&format!("let {} = {}.x;\n",
 			self.get_fragment_output(self_id),
 			node.get_fragment_output(self.node.id),
-		)
+		))
 	}
 
 	fn get_fragment_output(&self, self_id: usize) -> String {
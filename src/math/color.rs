@@ -1,6 +1,18 @@
 const ELEMENT_NUM: usize = 3;
 type Elements = [f32; ELEMENT_NUM];
 
+// Encoding of color values. Shading is done in Linear.
+pub enum ColorSpace {
+	Linear,
+	SRGB,
+}
+
+impl Default for ColorSpace {
+	fn default() -> Self {
+		ColorSpace::SRGB
+	}
+}
+
 pub struct Color {
 }
 
@@ -22,4 +34,15 @@ impl Color {
 		}
 		c
 	}
+
+	// Decodes sRGB encoded values to Linear
+	pub fn convert_srgb_to_linear(c: &mut Elements) -> &mut Elements {
+		for i in 0..ELEMENT_NUM {
+			c[i] = match c[i] <= 0.04045 {
+				true => c[i] * 0.0773993808,
+				false => (c[i] * 0.9478672986 + 0.0521327014).powf(2.4),
+			};
+		}
+		c
+	}
 }
@@ -1,7 +1,10 @@
 pub mod wgpu_attributes;
 pub mod wgpu_bindings;
 pub mod wgpu_indices;
+pub mod wgpu_instances;
+pub mod wgpu_interleaved_buffers;
 pub mod wgpu_render_pipeline;
 pub mod wgpu_renderer;
 pub mod wgpu_samplers;
 pub mod wgpu_textures;
+pub mod wgpu_vertex_layouts;
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+};
 
 use crate::{
 	geometry::attribute::Attribute,
@@ -8,8 +11,15 @@ use crate::{
 	},
 };
 
+struct Entry {
+	buffer: wgpu::Buffer,
+	last_used: u64,
+	size: usize,
+	version: u64,
+}
+
 pub struct WGPUAttributes {
-	attributes: HashMap<ResourceId<Attribute>, wgpu::Buffer>,
+	attributes: HashMap<ResourceId<Attribute>, Entry>,
 }
 
 impl WGPUAttributes {
@@ -20,26 +30,91 @@ impl WGPUAttributes {
 	}
 
 	pub fn borrow(&self, attribute: &ResourceId<Attribute>) -> Option<&wgpu::Buffer> {
-		self.attributes.get(attribute)
+		self.attributes.get(attribute).map(|entry| &entry.buffer)
 	}
 
-	// @TODO: Implement correctly
+	// Creates the buffer at first, and re-uploads the data when the attribute
+	// has been changed since. The buffer is recreated if the size differs
+	// or isn't aligned for write_buffer().
 	pub fn update(
 		&mut self,
 		device: &wgpu::Device,
+		queue: &wgpu::Queue,
 		pools: &ResourcePools,
 		attribute_rid: &ResourceId<Attribute>,
+		frame: u64,
 	) {
-		if !self.attributes.contains_key(attribute_rid) {
-			if let Some(attribute) = pools.borrow::<Attribute>().borrow(attribute_rid) {
-				self.attributes.insert(*attribute_rid, create_buffer(
-					device,
-					bytemuck::cast_slice(attribute.borrow_data()),
-					wgpu::BufferUsages::VERTEX,
-				));
+		let attribute = match pools.borrow::<Attribute>().borrow(attribute_rid) {
+			Some(attribute) => attribute,
+			None => return,
+		};
+
+		if let Some(entry) = self.attributes.get_mut(attribute_rid) {
+			entry.last_used = frame;
+			if entry.version == attribute.get_version() {
+				return;
+			}
+		}
+
+		let contents = build_contents(attribute);
+		let contents: &[u8] = &contents;
+
+		if let Some(entry) = self.attributes.get_mut(attribute_rid) {
+			if entry.size == contents.len() &&
+				contents.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize == 0 {
+				queue.write_buffer(&entry.buffer, 0, contents);
+				entry.version = attribute.get_version();
+				return;
 			}
 		}
+
+		self.attributes.insert(*attribute_rid, Entry {
+			buffer: create_buffer(
+				device,
+				contents,
+				wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+			),
+			last_used: frame,
+			size: contents.len(),
+			version: attribute.get_version(),
+		});
+	}
+
+	// Releases the buffers of removed attributes and of the ones
+	// not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<Attribute>();
+		self.attributes.retain(|rid, entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
+	}
+}
+
+// Pads items with zeros if needed. See Attribute::get_padded_item_size()
+// and Attribute::get_aligned_size(). The data is borrowed as is if no
+// padding is needed, otherwise it's repacked into a new buffer, a copy
+// of the whole data on each upload.
+fn build_contents(attribute: &Attribute) -> Cow<'_, [u8]> {
+	let bytes = attribute.borrow_data().borrow_bytes();
+
+	let item_bytes = (attribute.get_item_size() * attribute.get_type().get_size()) as usize;
+	let aligned_size = attribute.get_aligned_size() as usize;
+	if item_bytes == aligned_size {
+		return Cow::Borrowed(bytes);
+	}
+
+	let padding = vec![0; aligned_size - item_bytes];
+	let mut contents = Vec::with_capacity(attribute.get_count() as usize * aligned_size);
+	for item in bytes.chunks_exact(item_bytes) {
+		contents.extend_from_slice(item);
+		contents.extend_from_slice(&padding);
 	}
+	Cow::Owned(contents)
 }
 
 fn create_buffer(device: &wgpu::Device, contents: &[u8], usage: wgpu::BufferUsages) -> wgpu::Buffer {
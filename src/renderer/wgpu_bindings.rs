@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use crate::{
+	error::error::Result,
 	material::{
 		material::Material,
 		node::node::{
@@ -23,60 +24,61 @@ use crate::{
 	},
 	scene::{
 		camera::PerspectiveCamera,
+		mesh::Mesh,
 		node::Node,
 	},
 };
 
+// Per frame state bindings are built and updated with
+pub struct WGPUBindingContext<'a> {
+	pub camera: &'a PerspectiveCamera,
+	pub camera_node: &'a Node,
+	pub device: &'a wgpu::Device,
+	pub frame: u64,
+	pub pools: &'a ResourcePools,
+	pub queue: &'a wgpu::Queue,
+	pub samplers: &'a WGPUSamplers,
+	pub textures: &'a WGPUTextures,
+}
+
 pub struct WGPUBinding {
 	buffers: Vec<wgpu::Buffer>,
 	group: wgpu::BindGroup,
+	last_used: u64,
 	layout: wgpu::BindGroupLayout,
+	material: ResourceId<Material>,
+	material_version: u64,
+	// Serials of the GPU textures and samplers the group refers to
+	serials: Vec<u64>,
 }
 
 impl WGPUBinding {
 	fn new(
-		device: &wgpu::Device,
-		wgpu_textures: &WGPUTextures,
-		wgpu_samplers: &WGPUSamplers,
-		pools: &ResourcePools,
+		context: &WGPUBindingContext,
+		material_rid: &ResourceId<Material>,
 		material: &Material,
-	) -> Self {
-		let layout = Self::build_layout(device, pools, material);
-
-		let textures = material.borrow_textures(
-			pools.borrow::<Box<dyn MaterialNode>>(),
-		);
-		let mut textures_gpu = Vec::new();
-		for texture in textures.iter() {
-			if let Some(texture) = wgpu_textures.borrow(texture) {
-				textures_gpu.push(texture);
-			}
-		}
-
-		let samplers = material.borrow_samplers(
-			pools.borrow::<Box<dyn MaterialNode>>(),
-		);
-		let mut samplers_gpu = Vec::new();
-		for sampler in samplers.iter() {
-			if let Some(sampler) = wgpu_samplers.borrow(sampler) {
-				samplers_gpu.push(sampler);
-			}
-		}
-
-		let buffers = Self::build_buffers(device, pools, material);
-		let group = Self::build_group(
-			device,
+	) -> Result<Self> {
+		let layout = Self::build_layout(context.device, context.pools, material)?;
+		let buffers = Self::build_buffers(context.device, context.pools, material)?;
+		let group = Self::build_group_from_material(
+			context.device,
 			&layout,
 			&buffers,
-			&textures_gpu,
-			&samplers_gpu,
-		);
+			context.textures,
+			context.samplers,
+			context.pools,
+			material,
+		)?;
 
-		WGPUBinding {
+		Ok(WGPUBinding {
 			buffers: buffers,
 			group: group,
+			last_used: context.frame,
 			layout: layout,
-		}
+			material: *material_rid,
+			material_version: material.get_version(),
+			serials: collect_serials(context.textures, context.samplers, context.pools, material)?,
+		})
 	}
 
 	pub fn borrow_group(&self) -> &wgpu::BindGroup {
@@ -99,7 +101,7 @@ impl WGPUBinding {
 		camera: &PerspectiveCamera,
 		camera_node: &Node,
 		material: &Material,
-	) {
+	) -> Result<()> {
 		// @TODO: Is calculating them here inefficient?
 		let mut model_view_matrix = Matrix4::create();
 		let mut camera_matrix_inverse = Matrix4::create();
@@ -123,7 +125,7 @@ impl WGPUBinding {
 		// @TODO: Optimize
 		for contents in material.borrow_contents(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		).iter() {
+		)?.iter() {
 			match contents {
 				UniformContents::Float {value: _} |
 				UniformContents::Vector3 {value: _} |
@@ -157,13 +159,15 @@ impl WGPUBinding {
 				_ => {},
 			};
 		}
+
+		Ok(())
 	}
 
 	fn build_layout(
 		device: &wgpu::Device,
 		pools: &ResourcePools,
 		material: &Material
-	) -> wgpu::BindGroupLayout {
+	) -> Result<wgpu::BindGroupLayout> {
 		let mut entries = Vec::new();
 		let mut buffer_size = 0;
 		let mut max_align = 0;
@@ -176,7 +180,7 @@ impl WGPUBinding {
 
 		for contents in material.borrow_contents(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		).iter() {
+		)?.iter() {
 			match contents {
 				UniformContents::Float {..} |
 				UniformContents::Matrix4 {..} |
@@ -198,7 +202,7 @@ impl WGPUBinding {
 
 		for _texture in material.borrow_textures(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		).iter() {
+		)?.iter() {
 			entries.push(wgpu::BindGroupLayoutEntry {
 				binding: entries.len() as u32 + 3,
 				count: None,
@@ -217,7 +221,7 @@ impl WGPUBinding {
 		// @TODO: Fix me. Loop twice is inefficient
 		for _sampler in material.borrow_samplers(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		).iter() {
+		)?.iter() {
 			entries.push(wgpu::BindGroupLayoutEntry {
 				binding: entries.len() as u32 + 3,
 				count: None,
@@ -267,10 +271,48 @@ impl WGPUBinding {
 			visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
 		});
 
-		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+		Ok(device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			entries: &entries,
 			label: None,
-		})
+		}))
+	}
+
+	fn build_group_from_material(
+		device: &wgpu::Device,
+		layout: &wgpu::BindGroupLayout,
+		buffers: &Vec<wgpu::Buffer>,
+		wgpu_textures: &WGPUTextures,
+		wgpu_samplers: &WGPUSamplers,
+		pools: &ResourcePools,
+		material: &Material,
+	) -> Result<wgpu::BindGroup> {
+		let textures = material.borrow_textures(
+			pools.borrow::<Box<dyn MaterialNode>>(),
+		)?;
+		let mut textures_gpu = Vec::new();
+		for texture in textures.iter() {
+			if let Some(texture) = wgpu_textures.borrow(texture) {
+				textures_gpu.push(texture);
+			}
+		}
+
+		let samplers = material.borrow_samplers(
+			pools.borrow::<Box<dyn MaterialNode>>(),
+		)?;
+		let mut samplers_gpu = Vec::new();
+		for sampler in samplers.iter() {
+			if let Some(sampler) = wgpu_samplers.borrow(sampler) {
+				samplers_gpu.push(sampler);
+			}
+		}
+
+		Ok(Self::build_group(
+			device,
+			layout,
+			buffers,
+			&textures_gpu,
+			&samplers_gpu,
+		))
 	}
 
 	fn build_group(
@@ -319,7 +361,7 @@ impl WGPUBinding {
 		device: &wgpu::Device,
 		pools: &ResourcePools,
 		material: &Material,
-	) -> Vec<wgpu::Buffer> {
+	) -> Result<Vec<wgpu::Buffer>> {
 		let mut buffers = Vec::new();
 
 		// binding 0 : Object (model-view matrix, normal matrix)
@@ -332,7 +374,7 @@ impl WGPUBinding {
 		let mut buffer_size = 0;
 		for contents in material.borrow_contents(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		).iter() {
+		)?.iter() {
 			match contents {
 				UniformContents::Float {value: _} |
 				UniformContents::Matrix4 {value: _} |
@@ -346,15 +388,17 @@ impl WGPUBinding {
 		}
 
 		buffers.push(create_buffer(device, buffer_size as usize));
-		buffers
+		Ok(buffers)
 	}
 }
 
+// A node can have more than one mesh, a mesh can be assigned to more
+// than one node, and a mesh can have more than one material, so bindings
+// are per node, mesh and material index.
 pub struct WGPUBindings {
-	groups: HashMap<ResourceId<Node>, WGPUBinding>
+	groups: HashMap<(ResourceId<Node>, ResourceId<Mesh>, usize), WGPUBinding>
 }
 
-// @TODO: Implement correctly
 impl WGPUBindings {
 	pub fn new() -> Self {
 		WGPUBindings {
@@ -362,36 +406,116 @@ impl WGPUBindings {
 		}
 	}
 
-	pub fn borrow(&self, node: &ResourceId<Node>) -> Option<&WGPUBinding> {
-		self.groups.get(node)
+	pub fn borrow(
+		&self,
+		node: &ResourceId<Node>,
+		mesh: &ResourceId<Mesh>,
+		material_index: usize,
+	) -> Option<&WGPUBinding> {
+		self.groups.get(&(*node, *mesh, material_index))
+	}
+
+	pub fn remove(
+		&mut self,
+		node: &ResourceId<Node>,
+		mesh: &ResourceId<Mesh>,
+		material_index: usize,
+	) {
+		self.groups.remove(&(*node, *mesh, material_index));
 	}
 
+	// The binding is rebuilt when the material has been replaced or changed,
+	// and only the group is rebuilt when the textures or samplers have been
+	// recreated. Uniform values are written every time.
 	pub fn update(&mut self,
-		device: &wgpu::Device,
-		queue: &wgpu::Queue,
-		wgpu_textures: &WGPUTextures,
-		wgpu_samplers: &WGPUSamplers,
-		pools: &ResourcePools,
+		context: &WGPUBindingContext,
 		node_rid: &ResourceId<Node>,
-		camera: &PerspectiveCamera,
-		camera_node: &Node,
+		mesh_rid: &ResourceId<Mesh>,
+		material_index: usize,
+		material_rid: &ResourceId<Material>,
 		material: &Material,
-	) {
-		if !self.groups.contains_key(node_rid) {
-			self.groups.insert(*node_rid, WGPUBinding::new(
-				device,
-				wgpu_textures,
-				wgpu_samplers,
-				pools,
-				material
-			));
+	) -> Result<()> {
+		let key = (*node_rid, *mesh_rid, material_index);
+		let rebuild = match self.groups.get(&key) {
+			Some(binding) => binding.material != *material_rid ||
+				binding.material_version != material.get_version(),
+			None => true,
+		};
+
+		if rebuild {
+			self.groups.insert(key, WGPUBinding::new(context, material_rid, material)?);
+		} else {
+			let serials = collect_serials(context.textures, context.samplers, context.pools, material)?;
+			let binding = self.groups.get_mut(&key).unwrap();
+			binding.last_used = context.frame;
+			if binding.serials != serials {
+				binding.group = WGPUBinding::build_group_from_material(
+					context.device,
+					&binding.layout,
+					&binding.buffers,
+					context.textures,
+					context.samplers,
+					context.pools,
+					material,
+				)?;
+				binding.serials = serials;
+			}
+		}
+
+		if let Some(node) = context.pools.borrow::<Node>().borrow(node_rid) {
+			let binding = self.groups.get(&key).unwrap();
+			binding.update(
+				context.queue,
+				context.pools,
+				node,
+				context.camera,
+				context.camera_node,
+				material,
+			)?;
 		}
 
-		if let Some(node) = pools.borrow::<Node>().borrow(node_rid) {
-			let binding = self.groups.get(node_rid).unwrap();
-			binding.update(queue, pools, node, camera, camera_node, material);
+		Ok(())
+	}
+
+	// Releases the bindings of removed nodes and meshes and of
+	// the ones not drawn in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let node_pool = pools.borrow::<Node>();
+		let mesh_pool = pools.borrow::<Mesh>();
+		self.groups.retain(|(node, mesh, _), binding| {
+			node_pool.contains(node) && mesh_pool.contains(mesh) &&
+				frame - binding.last_used <= max_unused_frames
+		});
+	}
+}
+
+fn collect_serials(
+	wgpu_textures: &WGPUTextures,
+	wgpu_samplers: &WGPUSamplers,
+	pools: &ResourcePools,
+	material: &Material,
+) -> Result<Vec<u64>> {
+	let mut serials = Vec::new();
+	for texture in material.borrow_textures(
+		pools.borrow::<Box<dyn MaterialNode>>(),
+	)?.iter() {
+		if let Some(serial) = wgpu_textures.get_serial(texture) {
+			serials.push(serial);
+		}
+	}
+	for sampler in material.borrow_samplers(
+		pools.borrow::<Box<dyn MaterialNode>>(),
+	)?.iter() {
+		if let Some(serial) = wgpu_samplers.get_serial(sampler) {
+			serials.push(serial);
 		}
 	}
+	Ok(serials)
 }
 
 fn create_buffer(device: &wgpu::Device, size_in_byte: usize) -> wgpu::Buffer {
@@ -3,15 +3,25 @@
 use std::collections::HashMap;
 
 use crate::{
-	geometry::index::Index,
+	geometry::index::{
+		Index,
+		IndexData,
+	},
 	resource::resource::{
 		ResourceId,
 		ResourcePools,
 	},
 };
 
+struct Entry {
+	buffer: wgpu::Buffer,
+	last_used: u64,
+	size: usize,
+	version: u64,
+}
+
 pub struct WGPUIndices {
-	indices: HashMap<ResourceId<Index>, wgpu::Buffer>,
+	indices: HashMap<ResourceId<Index>, Entry>,
 }
 
 impl WGPUIndices {
@@ -22,25 +32,67 @@ impl WGPUIndices {
 	}
 
 	pub fn borrow(&self, index: &ResourceId<Index>) -> Option<&wgpu::Buffer> {
-		self.indices.get(index)
+		self.indices.get(index).map(|entry| &entry.buffer)
 	}
 
-	// @TODO: Implement correctly
+	// Creates the buffer at first, and re-uploads the data when the index
+	// has been changed since. The buffer is recreated if the size differs
+	// or isn't aligned for write_buffer().
 	pub fn update(
 		&mut self,
 		device: &wgpu::Device,
+		queue: &wgpu::Queue,
 		pools: &ResourcePools,
 		index_rid: &ResourceId<Index>,
+		frame: u64,
 	) {
-		if !self.indices.contains_key(index_rid) {
-			if let Some(index) = pools.borrow::<Index>().borrow(index_rid) {
-				self.indices.insert(*index_rid, create_buffer(
-					device,
-					bytemuck::cast_slice(index.borrow_data()),
-					wgpu::BufferUsages::INDEX,
-				));
+		let index = match pools.borrow::<Index>().borrow(index_rid) {
+			Some(index) => index,
+			None => return,
+		};
+
+		let contents: &[u8] = match index.borrow_data() {
+			IndexData::Uint16(data) => bytemuck::cast_slice(data),
+			IndexData::Uint32(data) => bytemuck::cast_slice(data),
+		};
+
+		if let Some(entry) = self.indices.get_mut(index_rid) {
+			entry.last_used = frame;
+			if entry.version == index.get_version() {
+				return;
+			}
+			if entry.size == contents.len() &&
+				contents.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize == 0 {
+				queue.write_buffer(&entry.buffer, 0, contents);
+				entry.version = index.get_version();
+				return;
 			}
 		}
+
+		self.indices.insert(*index_rid, Entry {
+			buffer: create_buffer(
+				device,
+				contents,
+				wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+			),
+			last_used: frame,
+			size: contents.len(),
+			version: index.get_version(),
+		});
+	}
+
+	// Releases the buffers of removed indices and of the ones
+	// not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<Index>();
+		self.indices.retain(|rid, entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
 	}
 }
 
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::{
+	scene::instanced_mesh::InstancedMesh,
+	resource::resource::{
+		ResourceId,
+		ResourcePools,
+	},
+};
+
+struct Entry {
+	buffer: wgpu::Buffer,
+	last_used: u64,
+	size: usize,
+	version: u64,
+}
+
+pub struct WGPUInstances {
+	buffers: HashMap<ResourceId<InstancedMesh>, Entry>,
+}
+
+impl WGPUInstances {
+	pub fn new() -> Self {
+		WGPUInstances {
+			buffers: HashMap::new()
+		}
+	}
+
+	pub fn borrow(&self, instanced_mesh: &ResourceId<InstancedMesh>) -> Option<&wgpu::Buffer> {
+		self.buffers.get(instanced_mesh).map(|entry| &entry.buffer)
+	}
+
+	// Creates the buffer at first, and re-uploads the instance data when the
+	// instanced mesh has been changed since. The buffer is recreated if the
+	// size differs or isn't aligned for write_buffer().
+	pub fn update(
+		&mut self,
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		pools: &ResourcePools,
+		instanced_mesh_rid: &ResourceId<InstancedMesh>,
+		frame: u64,
+	) {
+		let instanced_mesh = match pools.borrow::<InstancedMesh>().borrow(instanced_mesh_rid) {
+			Some(instanced_mesh) => instanced_mesh,
+			None => return,
+		};
+
+		if let Some(entry) = self.buffers.get_mut(instanced_mesh_rid) {
+			entry.last_used = frame;
+			if entry.version == instanced_mesh.get_version() {
+				return;
+			}
+		}
+
+		let contents = build_contents(instanced_mesh);
+		let contents: &[u8] = &contents;
+
+		if let Some(entry) = self.buffers.get_mut(instanced_mesh_rid) {
+			if entry.size == contents.len() &&
+				contents.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize == 0 {
+				queue.write_buffer(&entry.buffer, 0, contents);
+				entry.version = instanced_mesh.get_version();
+				return;
+			}
+		}
+
+		self.buffers.insert(*instanced_mesh_rid, Entry {
+			buffer: create_buffer(
+				device,
+				contents,
+				wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+			),
+			last_used: frame,
+			size: contents.len(),
+			version: instanced_mesh.get_version(),
+		});
+	}
+
+	// Releases the buffers of removed instanced meshes and of
+	// the ones not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<InstancedMesh>();
+		self.buffers.retain(|rid, entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
+	}
+}
+
+// Matrix and optional color per instance, in the layout of
+// VertexLayout::add_instance_attributes()
+fn build_contents(instanced_mesh: &InstancedMesh) -> Vec<u8> {
+	let colors = instanced_mesh.borrow_colors();
+	let mut contents = Vec::new();
+	for (i, matrix) in instanced_mesh.borrow_matrices().iter().enumerate() {
+		contents.extend_from_slice(bytemuck::cast_slice(matrix));
+		if let Some(colors) = colors {
+			contents.extend_from_slice(bytemuck::cast_slice(colors.get(i).unwrap_or(&[1.0; 3])));
+		}
+	}
+	contents
+}
+
+// @TODO: Remove duplication with wgpu_attributes.rs
+fn create_buffer(device: &wgpu::Device, contents: &[u8], usage: wgpu::BufferUsages) -> wgpu::Buffer {
+	use wgpu::util::DeviceExt;
+	device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: None,
+		contents:  contents,
+		usage: usage,
+	})
+}
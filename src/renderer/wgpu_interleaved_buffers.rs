@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::{
+	geometry::interleaved_buffer::InterleavedBuffer,
+	resource::resource::{
+		ResourceId,
+		ResourcePools,
+	},
+};
+
+struct Entry {
+	buffer: wgpu::Buffer,
+	last_used: u64,
+	size: usize,
+	version: u64,
+}
+
+pub struct WGPUInterleavedBuffers {
+	buffers: HashMap<ResourceId<InterleavedBuffer>, Entry>,
+}
+
+impl WGPUInterleavedBuffers {
+	pub fn new() -> Self {
+		WGPUInterleavedBuffers {
+			buffers: HashMap::new()
+		}
+	}
+
+	pub fn borrow(&self, buffer: &ResourceId<InterleavedBuffer>) -> Option<&wgpu::Buffer> {
+		self.buffers.get(buffer).map(|entry| &entry.buffer)
+	}
+
+	// Creates the buffer at first, and re-uploads the data when the interleaved
+	// buffer has been changed since. The buffer is recreated if the size differs
+	// or isn't aligned for write_buffer().
+	pub fn update(
+		&mut self,
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		pools: &ResourcePools,
+		buffer_rid: &ResourceId<InterleavedBuffer>,
+		frame: u64,
+	) {
+		let buffer = match pools.borrow::<InterleavedBuffer>().borrow(buffer_rid) {
+			Some(buffer) => buffer,
+			None => return,
+		};
+
+		let contents: &[u8] = buffer.borrow_data();
+
+		if let Some(entry) = self.buffers.get_mut(buffer_rid) {
+			entry.last_used = frame;
+			if entry.version == buffer.get_version() {
+				return;
+			}
+			if entry.size == contents.len() &&
+				contents.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize == 0 {
+				queue.write_buffer(&entry.buffer, 0, contents);
+				entry.version = buffer.get_version();
+				return;
+			}
+		}
+
+		self.buffers.insert(*buffer_rid, Entry {
+			buffer: create_buffer(
+				device,
+				contents,
+				wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+			),
+			last_used: frame,
+			size: contents.len(),
+			version: buffer.get_version(),
+		});
+	}
+
+	// Releases the buffers of removed interleaved buffers and of
+	// the ones not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<InterleavedBuffer>();
+		self.buffers.retain(|rid, entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
+	}
+}
+
+// @TODO: Remove duplication with wgpu_attributes.rs
+fn create_buffer(device: &wgpu::Device, contents: &[u8], usage: wgpu::BufferUsages) -> wgpu::Buffer {
+	use wgpu::util::DeviceExt;
+	device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: None,
+		contents:  contents,
+		usage: usage,
+	})
+}
@@ -1,7 +1,19 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{
+	HashMap,
+	HashSet,
+};
 
 use crate::{
+	geometry::{
+		attribute::AttributeType,
+		vertex_layout::{
+			VertexAttributeLayout,
+			VertexLayout,
+		},
+	},
+	error::error::Result,
+	math::color::ColorSpace,
 	material::{
 		material::{
 			Material,
@@ -9,31 +21,68 @@ use crate::{
 		},
 		node::node::MaterialNode,
 	},
+	renderer::wgpu_vertex_layouts::WGPUVertexLayout,
 	resource::resource::{
 		ResourceId,
 		ResourcePools,
 	},
-	scene::node::Node,
+	scene::{
+		instanced_mesh::InstancedMesh,
+		mesh::Mesh,
+		node::Node,
+	},
 };
 
+// Everything a render pipeline is built from. Draws with equal keys
+// share a pipeline. The bind group layout isn't in the key because
+// it's derived from the material as the shader code is, and wgpu
+// deduplicates bind group layouts of the same entries.
+#[derive(Clone, Eq, Hash, PartialEq)]
+struct PipelineKey {
+	blend: Option<wgpu::BlendState>,
+	format: wgpu::TextureFormat,
+	sample_count: u32,
+	shader_code: String,
+	side: Side,
+	vertex_layout: VertexLayout,
+}
+
+// Identifies a draw. Per node, mesh, material index and instanced mesh
+// if instanced. Instanced meshes of the same node and mesh can differ
+// in the vertex layout.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct DrawKey {
+	pub instanced: Option<ResourceId<InstancedMesh>>,
+	pub material_index: usize,
+	pub mesh: ResourceId<Mesh>,
+	pub node: ResourceId<Node>,
+}
+
+// Per frame state pipelines are built with. Format, output color space
+// and sample count are of the render target.
+pub struct WGPURenderPipelineContext<'a> {
+	pub device: &'a wgpu::Device,
+	pub format: wgpu::TextureFormat,
+	pub frame: u64,
+	pub output_color_space: &'a ColorSpace,
+	pub pools: &'a ResourcePools,
+	pub sample_count: u32,
+}
+
 pub struct WGPURenderPipeline {
-	pipeline: wgpu::RenderPipeline
+	pipeline: wgpu::RenderPipeline,
+	vertex_layout: VertexLayout,
 }
 
 impl WGPURenderPipeline {
 	fn new(
 		device: &wgpu::Device,
 		bind_group_layout: &wgpu::BindGroupLayout,
-		shader_code: &str,
-		sample_count: u32,
-		side: &Side,
+		key: &PipelineKey,
 	) -> Self {
-		// For debug
-		//println!("{}", shader_code);
-
 		let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
 			label: None,
-			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader_code)),
+			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&key.shader_code)),
 		});
 
 		let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -42,45 +91,35 @@ impl WGPURenderPipeline {
 			push_constant_ranges: &[],
 		});
 
-		// @TODO: Programmable
-		let vertex_buffers = [
-			// position
-			wgpu::VertexBufferLayout {
-				array_stride: 3 * 4,
-				step_mode: wgpu::VertexStepMode::Vertex,
-				attributes: &[
+		let vertex_layout = &key.vertex_layout;
+
+		let vertex_attributes = vertex_layout.borrow_buffers()
+			.iter()
+			.map(|buffer| buffer.borrow_attributes()
+				.iter()
+				.map(|location| {
+					let attribute = &vertex_layout.borrow_attributes()[*location];
 					wgpu::VertexAttribute {
-						format: wgpu::VertexFormat::Float32x3,
-						offset: 0,
-						shader_location: 0,
+						format: get_vertex_format(attribute),
+						offset: attribute.get_offset() as u64,
+						shader_location: *location as u32,
 					}
-				],
-			},
-			// normal
-			wgpu::VertexBufferLayout {
-				array_stride: 3 * 4,
-				step_mode: wgpu::VertexStepMode::Vertex,
-				attributes: &[
-					wgpu::VertexAttribute {
-						format: wgpu::VertexFormat::Float32x3,
-						offset: 0,
-						shader_location: 1,
-					},
-				],
-			},
-			// uv
-			wgpu::VertexBufferLayout {
-				array_stride: 2 * 4,
-				step_mode: wgpu::VertexStepMode::Vertex,
-				attributes: &[
-					wgpu::VertexAttribute {
-						format: wgpu::VertexFormat::Float32x2,
-						offset: 0,
-						shader_location: 2,
-					},
-				],
-			},
-		];
+				})
+				.collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+
+		let vertex_buffers = vertex_layout.borrow_buffers()
+			.iter()
+			.zip(vertex_attributes.iter())
+			.map(|(buffer, vertex_attributes)| wgpu::VertexBufferLayout {
+				array_stride: buffer.get_stride() as u64,
+				step_mode: match buffer.is_instanced() {
+					true => wgpu::VertexStepMode::Instance,
+					false => wgpu::VertexStepMode::Vertex,
+				},
+				attributes: vertex_attributes,
+			})
+			.collect::<Vec<_>>();
 
 		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
 			label: None,
@@ -93,18 +132,21 @@ impl WGPURenderPipeline {
 			fragment: Some(wgpu::FragmentState {
 				module: &shader,
 				entry_point: "fs_main",
-				// @TODO: Color management
-				targets: &[wgpu::TextureFormat::Bgra8Unorm.into()],
+				targets: &[wgpu::ColorTargetState {
+					format: key.format,
+					blend: key.blend,
+					write_mask: wgpu::ColorWrites::ALL,
+				}],
 			}),
 			// Backface culling
-			// @TODO: Should be configurable 
+			// @TODO: Should be configurable
 			primitive: wgpu::PrimitiveState {
-				cull_mode: match side {
-					Side::BackSide | 
+				cull_mode: match key.side {
+					Side::BackSide |
 					Side::FrontSide => Some(wgpu::Face::Back),
 					Side::DoubleSide => None,
 				},
-				front_face: match side {
+				front_face: match key.side {
 					Side::BackSide => wgpu::FrontFace::Cw,
 					Side::DoubleSide |
 					Side::FrontSide => wgpu::FrontFace::Ccw,
@@ -119,58 +161,205 @@ impl WGPURenderPipeline {
 				stencil: wgpu::StencilState::default(),
 			}),
 			multisample: wgpu::MultisampleState {
-				count: sample_count,
+				count: key.sample_count,
 				..Default::default()
 			},
 		});
 
 		WGPURenderPipeline {
-			pipeline: pipeline
+			pipeline: pipeline,
+			vertex_layout: vertex_layout.clone(),
 		}
 	}
+
+	pub fn borrow_pipeline(&self) -> &wgpu::RenderPipeline {
+		&self.pipeline
+	}
+
+	pub fn borrow_vertex_layout(&self) -> &VertexLayout {
+		&self.vertex_layout
+	}
+}
+
+// Which pipeline a draw uses, and what it has been chosen from
+struct Draw {
+	last_used: u64,
+	material: ResourceId<Material>,
+	material_version: u64,
+	pipeline: u64,
+	vertex_layout: u64,
+}
+
+// Numbers of the pipeline cache for debugging and profiling
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WGPURenderPipelineStats {
+	// Per node, mesh, material index and instanced mesh
+	pub draws: usize,
+	// Less than draws if some draws share pipelines
+	pub pipelines: usize,
+	// Times a draw has found an existing pipeline
+	pub hits: u64,
+	// Times a pipeline has been created
+	pub misses: u64,
 }
 
+// Draws are per node, mesh, material index and instanced mesh.
+// Draws of the same shader code and render state share a pipeline.
 pub struct WGPURenderPipelines {
-	pipelines: HashMap::<ResourceId<Node>, WGPURenderPipeline>
+	draws: HashMap<DrawKey, Draw>,
+	ids: HashMap<PipelineKey, u64>,
+	next_id: u64,
+	pipelines: HashMap<u64, WGPURenderPipeline>,
+	stats: WGPURenderPipelineStats,
 }
 
 impl WGPURenderPipelines {
 	pub fn new() -> Self {
 		WGPURenderPipelines {
-			pipelines: HashMap::new()
+			draws: HashMap::new(),
+			ids: HashMap::new(),
+			next_id: 0,
+			pipelines: HashMap::new(),
+			stats: WGPURenderPipelineStats::default(),
 		}
 	}
 
-	pub fn borrow(&self, node: &ResourceId<Node>) -> Option<&wgpu::RenderPipeline> {
-		if let Some(pipeline) = &self.pipelines.get(node) {
-			Some(&pipeline.pipeline)
-		} else {
-			None
+	pub fn borrow(&self, key: &DrawKey) -> Option<&WGPURenderPipeline> {
+		self.draws.get(key)
+			.and_then(|draw| self.pipelines.get(&draw.pipeline))
+	}
+
+	// The pipeline is released by collect_garbage() if no other draw uses it
+	pub fn remove(&mut self, key: &DrawKey) {
+		self.draws.remove(key);
+	}
+
+	pub fn get_stats(&self) -> WGPURenderPipelineStats {
+		WGPURenderPipelineStats {
+			draws: self.draws.len(),
+			pipelines: self.pipelines.len(),
+			..self.stats
 		}
 	}
 
+	// The pipeline of the draw is looked up again when the material has been
+	// replaced or changed, or the vertex layout of the geometry has been
+	// changed. The shader code is generated only then.
 	pub fn update(
 		&mut self,
-		device: &wgpu::Device,
-		pools: &ResourcePools,
-		node: &ResourceId<Node>,
+		context: &WGPURenderPipelineContext,
+		draw_key: &DrawKey,
+		material_rid: &ResourceId<Material>,
 		material: &Material,
 		bind_group_layout: &wgpu::BindGroupLayout,
-		sample_count: u32,
-	) {
-		if !self.pipelines.contains_key(&node) {
-			self.pipelines.insert(
-				*node,
-				WGPURenderPipeline::new(
-					device,
-					bind_group_layout,
-					&material.build_shader_code(
-						pools.borrow::<Box<dyn MaterialNode>>(),
-					),
-					sample_count,
-					material.borrow_side(),
-				)
-			);
+		vertex_layout: &WGPUVertexLayout,
+	) -> Result<()> {
+		if let Some(draw) = self.draws.get_mut(draw_key) {
+			draw.last_used = context.frame;
+			if draw.material == *material_rid &&
+				draw.material_version == material.get_version() &&
+				draw.vertex_layout == vertex_layout.get_id() {
+				return Ok(());
+			}
 		}
+
+		let key = PipelineKey {
+			// @TODO: From the material once it supports transparency
+			blend: None,
+			format: context.format,
+			sample_count: context.sample_count,
+			shader_code: material.build_shader_code(
+				context.pools.borrow::<Box<dyn MaterialNode>>(),
+				context.output_color_space,
+				vertex_layout.borrow_layout(),
+			)?,
+			side: *material.borrow_side(),
+			vertex_layout: vertex_layout.borrow_layout().clone(),
+		};
+
+		let id = match self.ids.get(&key) {
+			Some(id) => {
+				self.stats.hits += 1;
+				*id
+			},
+			None => {
+				self.stats.misses += 1;
+				let id = self.next_id;
+				self.next_id += 1;
+				self.pipelines.insert(id, WGPURenderPipeline::new(
+					context.device,
+					bind_group_layout,
+					&key,
+				));
+				self.ids.insert(key, id);
+				id
+			},
+		};
+
+		self.draws.insert(*draw_key, Draw {
+			last_used: context.frame,
+			material: *material_rid,
+			material_version: material.get_version(),
+			pipeline: id,
+			vertex_layout: vertex_layout.get_id(),
+		});
+
+		Ok(())
+	}
+
+	// Releases the draws of removed nodes, meshes and instanced meshes and
+	// of the ones not drawn in the last max_unused_frames frames, and the
+	// pipelines no draw uses any more
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let node_pool = pools.borrow::<Node>();
+		let mesh_pool = pools.borrow::<Mesh>();
+		let instanced_mesh_pool = pools.borrow::<InstancedMesh>();
+		self.draws.retain(|key, draw| {
+			node_pool.contains(&key.node) && mesh_pool.contains(&key.mesh) &&
+				key.instanced.map_or(true, |rid| instanced_mesh_pool.contains(&rid)) &&
+				frame - draw.last_used <= max_unused_frames
+		});
+
+		let used = self.draws.values().map(|draw| draw.pipeline).collect::<HashSet<_>>();
+		self.pipelines.retain(|id, _| used.contains(id));
+		self.ids.retain(|_, id| used.contains(id));
+	}
+}
+
+fn get_vertex_format(attribute: &VertexAttributeLayout) -> wgpu::VertexFormat {
+	let size = attribute.get_padded_item_size();
+	let normalized = attribute.is_normalized();
+	match (attribute.get_attribute_type(), normalized, size) {
+		(AttributeType::Float16, _, 2) => wgpu::VertexFormat::Float16x2,
+		(AttributeType::Float16, _, _) => wgpu::VertexFormat::Float16x4,
+		(AttributeType::Float32, _, 1) => wgpu::VertexFormat::Float32,
+		(AttributeType::Float32, _, 2) => wgpu::VertexFormat::Float32x2,
+		(AttributeType::Float32, _, 3) => wgpu::VertexFormat::Float32x3,
+		(AttributeType::Float32, _, _) => wgpu::VertexFormat::Float32x4,
+		(AttributeType::Sint8, false, 2) => wgpu::VertexFormat::Sint8x2,
+		(AttributeType::Sint8, false, _) => wgpu::VertexFormat::Sint8x4,
+		(AttributeType::Sint8, true, 2) => wgpu::VertexFormat::Snorm8x2,
+		(AttributeType::Sint8, true, _) => wgpu::VertexFormat::Snorm8x4,
+		(AttributeType::Sint16, false, 2) => wgpu::VertexFormat::Sint16x2,
+		(AttributeType::Sint16, false, _) => wgpu::VertexFormat::Sint16x4,
+		(AttributeType::Sint16, true, 2) => wgpu::VertexFormat::Snorm16x2,
+		(AttributeType::Sint16, true, _) => wgpu::VertexFormat::Snorm16x4,
+		(AttributeType::Uint8, false, 2) => wgpu::VertexFormat::Uint8x2,
+		(AttributeType::Uint8, false, _) => wgpu::VertexFormat::Uint8x4,
+		(AttributeType::Uint8, true, 2) => wgpu::VertexFormat::Unorm8x2,
+		(AttributeType::Uint8, true, _) => wgpu::VertexFormat::Unorm8x4,
+		(AttributeType::Uint16, false, 2) => wgpu::VertexFormat::Uint16x2,
+		(AttributeType::Uint16, false, _) => wgpu::VertexFormat::Uint16x4,
+		(AttributeType::Uint16, true, 2) => wgpu::VertexFormat::Unorm16x2,
+		(AttributeType::Uint16, true, _) => wgpu::VertexFormat::Unorm16x4,
+		(AttributeType::Uint32, _, 1) => wgpu::VertexFormat::Uint32,
+		(AttributeType::Uint32, _, 2) => wgpu::VertexFormat::Uint32x2,
+		(AttributeType::Uint32, _, 3) => wgpu::VertexFormat::Uint32x3,
+		(AttributeType::Uint32, _, _) => wgpu::VertexFormat::Uint32x4,
 	}
 }
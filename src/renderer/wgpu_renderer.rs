@@ -1,19 +1,47 @@
+use std::ops::Range;
+
 use winit::window::Window;
 
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	geometry::{
 		attribute::Attribute,
 		geometry::Geometry,
-		index::Index,
+		index::{
+			Index,
+			IndexFormat,
+		},
+		interleaved_buffer::InterleavedBuffer,
+	},
+	material::{
+		material::Material,
+		node::node::MaterialNode,
+	},
+	math::color::{
+		Color,
+		ColorSpace,
 	},
-	material::material::Material,
 	renderer::{
 		wgpu_attributes::WGPUAttributes,
-		wgpu_bindings::WGPUBindings,
+		wgpu_bindings::{
+			WGPUBindingContext,
+			WGPUBindings,
+		},
 		wgpu_indices::WGPUIndices,
-		wgpu_render_pipeline::WGPURenderPipelines,
+		wgpu_instances::WGPUInstances,
+		wgpu_interleaved_buffers::WGPUInterleavedBuffers,
+		wgpu_render_pipeline::{
+			DrawKey,
+			WGPURenderPipelineContext,
+			WGPURenderPipelineStats,
+			WGPURenderPipelines,
+		},
 		wgpu_samplers::WGPUSamplers,
 		wgpu_textures::WGPUTextures,
+		wgpu_vertex_layouts::WGPUVertexLayouts,
 	},
 	resource::resource::{
 		ResourceId,
@@ -21,92 +49,253 @@ use crate::{
 	},
 	scene::{
 		camera::PerspectiveCamera,
+		instanced_mesh::InstancedMesh,
 		mesh::Mesh,
 		node::Node,
 		scene::Scene,
 	},
+	texture::texture::{
+		Texture,
+		TextureFormat,
+	},
 };
 
 pub struct WGPURendererOptions {
+	pub backends: wgpu::Backends,
+	// Optional features. Features the adapter doesn't support are ignored.
+	// Use get_features() to know which ones are enabled.
+	pub features: wgpu::Features,
+	pub force_fallback_adapter: bool,
+	// None means the downlevel defaults with the adapter's texture size limits
+	pub limits: Option<wgpu::Limits>,
+	// Color space the final frame is encoded in. With SRGB an sRGB target
+	// format is preferred so that the encoding is done by the hardware,
+	// otherwise the shader encodes.
+	pub output_color_space: ColorSpace,
+	pub power_preference: wgpu::PowerPreference,
+	// Fifo is vsync and always supported. Unsupported modes fall back to Fifo.
+	// Ignored by offscreen renderer.
+	pub present_mode: wgpu::PresentMode,
+	// GPU objects of resources not used for this many frames are released
+	// on render(). Those of removed resources are released too. None disables
+	// it, use collect_garbage() then.
+	pub release_unused_after_frames: Option<u64>,
 	pub sample_count: u32,
 }
 
 impl Default for WGPURendererOptions {
 	fn default() -> Self {
 		WGPURendererOptions {
+			backends: wgpu::Backends::all(),
+			features: wgpu::Features::empty(),
+			force_fallback_adapter: false,
+			limits: None,
+			output_color_space: ColorSpace::SRGB,
+			power_preference: wgpu::PowerPreference::default(),
+			present_mode: wgpu::PresentMode::Fifo,
+			release_unused_after_frames: Some(60),
 			sample_count: 4,
 		}
 	}
 }
 
+// Where the final frame goes. Offscreen target is a single sampled
+// texture owned by the renderer so that it can be read back later.
+enum RenderTarget {
+	Offscreen(wgpu::Texture),
+	Surface(wgpu::Surface),
+}
+
+// Buffer a texture is copied into so that it can be mapped on CPU.
+// Rows are padded to wgpu::COPY_BYTES_PER_ROW_ALIGNMENT.
+struct ReadBackBuffer {
+	buffer: wgpu::Buffer,
+	format: wgpu::TextureFormat,
+	height: u32,
+	padded_bytes_per_row: u32,
+	width: u32,
+}
+
+impl ReadBackBuffer {
+	// Returns tightly packed rows
+	async fn read(&self, device: &wgpu::Device) -> Result<Vec<u8>> {
+		let unpadded_bytes_per_row = (self.width * 4) as usize;
+		let slice = self.buffer.slice(..);
+		let mapping = slice.map_async(wgpu::MapMode::Read);
+		device.poll(wgpu::Maintain::Wait);
+		mapping.await?;
+
+		let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+		{
+			let data = slice.get_mapped_range();
+			for row in data.chunks(self.padded_bytes_per_row as usize) {
+				pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+			}
+		}
+		self.buffer.unmap();
+
+		Ok(pixels)
+	}
+}
+
 pub struct WGPURenderer {
+	adapter_info: wgpu::AdapterInfo,
 	attributes: WGPUAttributes,
 	bindings: WGPUBindings,
+	capture_requested: bool,
+	captured_frame: Option<ReadBackBuffer>,
 	color_buffer: wgpu::Texture,
 	device: wgpu::Device,
 	depth_buffer: wgpu::Texture,
+	frame: u64,
 	height: f64,
 	indices: WGPUIndices,
+	instances: WGPUInstances,
+	interleaved_buffers: WGPUInterleavedBuffers,
+	output_color_space: ColorSpace,
 	pixel_ratio: f64,
 	queue: wgpu::Queue,
+	release_unused_after_frames: Option<u64>,
 	render_pipelines: WGPURenderPipelines,
 	sample_count: u32,
 	samplers: WGPUSamplers,
-	surface: wgpu::Surface,
 	surface_configuration: wgpu::SurfaceConfiguration,
+	target: RenderTarget,
 	textures: WGPUTextures,
+	vertex_layouts: WGPUVertexLayouts,
 	width: f64,
 }
 
 impl WGPURenderer {
-	pub async fn new(window: &Window, options: WGPURendererOptions) -> Self {
+	pub async fn new(window: &Window, options: WGPURendererOptions) -> Result<Self> {
 		let width = 640.0;
 		let height = 480.0;
 		let pixel_ratio = 1.0;
 
-		let instance = wgpu::Instance::new(wgpu::Backends::all());
+		let instance = wgpu::Instance::new(options.backends);
 		let surface = unsafe { instance.create_surface(window) };
 		let adapter = instance
 			.request_adapter(&wgpu::RequestAdapterOptions {
 				compatible_surface: Some(&surface),
-				force_fallback_adapter: false,
-				power_preference: wgpu::PowerPreference::default(),
+				force_fallback_adapter: options.force_fallback_adapter,
+				power_preference: options.power_preference,
 			})
 			.await
-			.expect("Failed to find an appropriate adapter");
-
-		let (device, queue) = adapter
-			.request_device(
-				&wgpu::DeviceDescriptor {
-					features: wgpu::Features::empty(),
-					label: None,
-					limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
-				},
-				None,
-			)
-			.await
-			.expect("Failed to create device");
+			.ok_or(Error::NoAdapter)?;
+
+		let (device, queue) = request_device(&adapter, &options).await?;
+
+		let format = surface
+			.get_preferred_format(&adapter)
+			.unwrap_or(wgpu::TextureFormat::Bgra8Unorm);
 
 		let surface_configuration = wgpu::SurfaceConfiguration {
-			// @TODO: Color management
-			format: wgpu::TextureFormat::Bgra8Unorm,
+			format: select_format(format, &options.output_color_space),
 			height: (height * pixel_ratio) as u32,
-			present_mode: wgpu::PresentMode::Mailbox,
-			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+			present_mode: options.present_mode,
+			usage: get_surface_usage(&adapter),
 			width: (width * pixel_ratio) as u32,
 		};
 
 		surface.configure(&device, &surface_configuration);
 
+		Ok(Self::new_internal(
+			&adapter,
+			device,
+			queue,
+			RenderTarget::Surface(surface),
+			surface_configuration,
+			width,
+			height,
+			pixel_ratio,
+			options,
+		))
+	}
+
+	// Renders into an owned texture instead of a window surface.
+	// Falls back to a software adapter if no hardware adapter is found
+	// so that it can run on machines without GPU. Set
+	// options.force_fallback_adapter to always use the software adapter.
+	pub async fn new_offscreen(
+		width: f64,
+		height: f64,
+		options: WGPURendererOptions,
+	) -> Result<Self> {
+		let pixel_ratio = 1.0;
+
+		let instance = wgpu::Instance::new(options.backends);
+		let adapter = match instance
+			.request_adapter(&wgpu::RequestAdapterOptions {
+				compatible_surface: None,
+				force_fallback_adapter: options.force_fallback_adapter,
+				power_preference: options.power_preference,
+			})
+			.await {
+			Some(adapter) => adapter,
+			None => instance
+				.request_adapter(&wgpu::RequestAdapterOptions {
+					compatible_surface: None,
+					force_fallback_adapter: true,
+					power_preference: options.power_preference,
+				})
+				.await
+				.ok_or(Error::NoAdapter)?,
+		};
+
+		let (device, queue) = request_device(&adapter, &options).await?;
+
+		// Rgba8 so that read back pixels can be used as RGBA8 as they are.
+		// Present mode doesn't matter but is kept for get_present_mode().
+		let surface_configuration = wgpu::SurfaceConfiguration {
+			format: select_format(
+				wgpu::TextureFormat::Rgba8UnormSrgb,
+				&options.output_color_space,
+			),
+			height: (height * pixel_ratio) as u32,
+			present_mode: options.present_mode,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+			width: (width * pixel_ratio) as u32,
+		};
+
+		let offscreen_buffer = create_offscreen_buffer(&device, &surface_configuration);
+
+		Ok(Self::new_internal(
+			&adapter,
+			device,
+			queue,
+			RenderTarget::Offscreen(offscreen_buffer),
+			surface_configuration,
+			width,
+			height,
+			pixel_ratio,
+			options,
+		))
+	}
+
+	fn new_internal(
+		adapter: &wgpu::Adapter,
+		device: wgpu::Device,
+		queue: wgpu::Queue,
+		target: RenderTarget,
+		surface_configuration: wgpu::SurfaceConfiguration,
+		width: f64,
+		height: f64,
+		pixel_ratio: f64,
+		options: WGPURendererOptions,
+	) -> Self {
 		WGPURenderer {
+			adapter_info: adapter.get_info(),
 			attributes: WGPUAttributes::new(),
 			bindings: WGPUBindings::new(),
+			capture_requested: false,
+			captured_frame: None,
 			color_buffer: create_color_buffer(
 				&device,
 				width,
 				height,
 				pixel_ratio,
 				options.sample_count,
+				surface_configuration.format,
 			),
 			depth_buffer: create_depth_buffer(
 				&device,
@@ -116,16 +305,22 @@ impl WGPURenderer {
 				options.sample_count,
 			),
 			device: device,
+			frame: 0,
 			height: height,
 			indices: WGPUIndices::new(),
+			instances: WGPUInstances::new(),
+			interleaved_buffers: WGPUInterleavedBuffers::new(),
+			output_color_space: options.output_color_space,
 			pixel_ratio: pixel_ratio,
 			queue: queue,
+			release_unused_after_frames: options.release_unused_after_frames,
 			render_pipelines: WGPURenderPipelines::new(),
 			sample_count: options.sample_count,
 			samplers: WGPUSamplers::new(),
-			surface: surface,
 			surface_configuration: surface_configuration,
+			target: target,
 			textures: WGPUTextures::new(),
+			vertex_layouts: WGPUVertexLayouts::new(),
 			width: width
 		}
 	}
@@ -134,6 +329,12 @@ impl WGPURenderer {
 		self.width = width;
 		self.height = height;
 
+		// Zero sized surface and buffers can't be created, for example while
+		// the window is minimized. Keep the current ones and skip rendering.
+		if !self.has_drawable_size() {
+			return self;
+		}
+
 		self.update_surface_configuration();
 		self.recreate_color_buffer();
 		self.recreate_depth_buffer();
@@ -154,43 +355,93 @@ impl WGPURenderer {
 		self.pixel_ratio
 	}
 
+	// Unsupported modes fall back to Fifo. wgpu 0.11 doesn't tell
+	// which modes the surface supports, so get_present_mode()
+	// returns the requested mode.
+	pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) -> &mut Self {
+		if self.surface_configuration.present_mode != present_mode {
+			self.surface_configuration.present_mode = present_mode;
+			if let RenderTarget::Surface(surface) = &self.target {
+				if self.has_drawable_size() {
+					surface.configure(&self.device, &self.surface_configuration);
+				}
+			}
+		}
+		self
+	}
+
+	pub fn get_present_mode(&self) -> wgpu::PresentMode {
+		self.surface_configuration.present_mode
+	}
+
+	pub fn get_output_color_space(&self) -> &ColorSpace {
+		&self.output_color_space
+	}
+
+	// sRGB target formats encode on write so that
+	// the shader needs to output linear values.
+	fn get_shader_output_color_space(&self) -> ColorSpace {
+		match self.output_color_space {
+			ColorSpace::Linear => ColorSpace::Linear,
+			ColorSpace::SRGB => match self.surface_configuration.format.describe().srgb {
+				true => ColorSpace::Linear,
+				false => ColorSpace::SRGB,
+			},
+		}
+	}
+
+	// Name, backend, device type and so on of the selected adapter
+	pub fn borrow_adapter_info(&self) -> &wgpu::AdapterInfo {
+		&self.adapter_info
+	}
+
+	// Features enabled on the device
+	pub fn get_features(&self) -> wgpu::Features {
+		self.device.features()
+	}
+
+	pub fn get_limits(&self) -> wgpu::Limits {
+		self.device.limits()
+	}
+
+	// How many render pipelines are alive and how often they are shared
+	pub fn get_render_pipeline_stats(&self) -> WGPURenderPipelineStats {
+		self.render_pipelines.get_stats()
+	}
+
 	fn update(
 		&mut self,
 		pools: &ResourcePools,
 		scene_rid: &ResourceId<Scene>,
 		camera_rid: &ResourceId<PerspectiveCamera>,
-	) {
-		let geometry_pool = pools.borrow::<Geometry>();
-		let mesh_pool = pools.borrow::<Mesh>();
-		let material_pool = pools.borrow::<Material>();
-		let node_pool = pools.borrow::<Node>();
+	) -> Result<()> {
+		self.frame += 1;
 
-		// @TODO: Error handling
+		let geometry_pool = pools.try_borrow::<Geometry>()?;
+		let instanced_mesh_pool = pools.try_borrow::<InstancedMesh>()?;
+		let mesh_pool = pools.try_borrow::<Mesh>()?;
+		let material_pool = pools.try_borrow::<Material>()?;
+		let material_node_pool = pools.try_borrow::<Box<dyn MaterialNode>>()?;
+		let node_pool = pools.try_borrow::<Node>()?;
 
-		let scene = match pools.borrow::<Scene>().borrow(scene_rid) {
-			Some(scene) => scene,
-			None => return,
-		};
+		let scene = pools.try_borrow::<Scene>()?
+			.borrow(scene_rid)
+			.ok_or(Error::ResourceNotFound("Scene"))?;
 
-		let camera = match pools.borrow::<PerspectiveCamera>().borrow(camera_rid) {
-			Some(camera) => camera,
-			None => return,
-		};
+		let camera = pools.try_borrow::<PerspectiveCamera>()?
+			.borrow(camera_rid)
+			.ok_or(Error::ResourceNotFound("PerspectiveCamera"))?;
 
-		let camera_node = match scene.borrow_assigned_from::<PerspectiveCamera>(camera_rid) {
-			Some(rid) => match node_pool.borrow(rid) {
-				Some(node) => node,
-				None => return,
-			},
-			None => return,
-		};
+		let camera_node = scene.borrow_assigned_from::<PerspectiveCamera>(camera_rid)
+			.and_then(|rid| node_pool.borrow(rid))
+			.ok_or(Error::ResourceNotFound("Node assigned to PerspectiveCamera"))?;
 
-		for node_rid in scene.collect_nodes(pools).iter() {
-			let mesh = match scene.borrow_assigned_to::<Mesh>(node_rid) {
-				Some(rid) => match mesh_pool.borrow(rid) {
-					Some(mesh) => mesh,
-					None => continue,
-				},
+		let limits = self.device.limits();
+		let output_color_space = self.get_shader_output_color_space();
+
+		for (node_rid, mesh_rid, instanced_mesh_rid) in collect_meshes(pools, scene).iter() {
+			let mesh = match mesh_pool.borrow(mesh_rid) {
+				Some(mesh) => mesh,
 				None => continue,
 			};
 
@@ -199,186 +450,352 @@ impl WGPURenderer {
 				None => continue,
 			};
 
-			let material = match material_pool.borrow(mesh.borrow_material()) {
-				Some(material) => material,
-				None => continue,
+			let instance_color = match instanced_mesh_rid {
+				Some(rid) => {
+					let instanced_mesh = match instanced_mesh_pool.borrow(rid) {
+						Some(instanced_mesh) => instanced_mesh,
+						None => continue,
+					};
+					if instanced_mesh.get_count() == 0 {
+						continue;
+					}
+					self.instances.update(&self.device, &self.queue, pools, rid, self.frame);
+					Some(instanced_mesh.borrow_colors().is_some())
+				},
+				None => None,
 			};
 
-			// @TODO: Implement correctly
-			if let Some(rid) = geometry.borrow_attribute("position") {
-				self.attributes.update(&self.device, pools, rid);
+			self.vertex_layouts.update(
+				pools,
+				mesh.borrow_geometry(),
+				instance_color,
+				&limits,
+				self.frame,
+			)?;
+
+			let vertex_layout = match self.vertex_layouts.borrow(mesh.borrow_geometry(), instance_color) {
+				Some(vertex_layout) => vertex_layout,
+				None => continue,
+			};
+			if vertex_layout.borrow_layout().find("position").is_none() {
+				continue;
 			}
-			if let Some(rid) = geometry.borrow_attribute("normal") {
-				self.attributes.update(&self.device, pools, rid);
+
+			for rid in geometry.borrow_attributes().values() {
+				self.attributes.update(&self.device, &self.queue, pools, rid, self.frame);
 			}
-			if let Some(rid) = geometry.borrow_attribute("uv") {
-				self.attributes.update(&self.device, pools, rid);
+
+			for attribute in geometry.borrow_interleaved_attributes().values() {
+				self.interleaved_buffers.update(
+					&self.device,
+					&self.queue,
+					pools,
+					attribute.borrow_buffer(),
+					self.frame,
+				);
 			}
 
 			if let Some(rid) = geometry.borrow_index() {
-				self.indices.update(&self.device, pools, rid);
+				self.indices.update(&self.device, &self.queue, pools, rid, self.frame);
 			}
 
-			self.textures.update_from_material(
-				&self.device,
-				&self.queue,
-				pools,
-				material,
-			);
+			// Only the materials drawn in this frame
+			let mut material_indices = get_draw_ranges(pools, geometry)
+				.into_iter()
+				.map(|(material_index, _)| material_index)
+				.collect::<Vec<_>>();
+			material_indices.sort();
+			material_indices.dedup();
 
-			self.samplers.update_from_material(
-				&self.device,
-				pools,
-				material,
-			);
-
-			self.bindings.update(
-				&self.device,
-				&self.queue,
-				&self.textures,
-				&self.samplers,
-				pools,
-				node_rid,
-				camera,
-				camera_node,
-				material,
-			);
-
-			self.render_pipelines.update(
-				&self.device,
-				pools,
-				node_rid,
-				material,
-				&self.bindings.borrow(node_rid).unwrap().borrow_layout(),
-				self.sample_count,
-			);
+			for material_index in material_indices.into_iter() {
+				let draw_key = DrawKey {
+					instanced: *instanced_mesh_rid,
+					material_index: material_index,
+					mesh: *mesh_rid,
+					node: *node_rid,
+				};
+
+				// The draw is dropped if the material or any node of it
+				// has been removed
+				let material = mesh.borrow_material_at(material_index)
+					.and_then(|rid| material_pool.borrow(rid).map(|material| (rid, material)))
+					.filter(|(_, material)| material.borrow_contents(material_node_pool).is_ok());
+				let (material_rid, material) = match material {
+					Some(material) => material,
+					None => {
+						self.bindings.remove(node_rid, mesh_rid, material_index);
+						self.render_pipelines.remove(&draw_key);
+						continue;
+					},
+				};
+
+				self.textures.update_from_material(
+					&self.device,
+					&self.queue,
+					pools,
+					material,
+					self.frame,
+				)?;
+
+				self.samplers.update_from_material(
+					&self.device,
+					pools,
+					material,
+					self.frame,
+				)?;
+
+				self.bindings.update(
+					&WGPUBindingContext {
+						camera: camera,
+						camera_node: camera_node,
+						device: &self.device,
+						frame: self.frame,
+						pools: pools,
+						queue: &self.queue,
+						samplers: &self.samplers,
+						textures: &self.textures,
+					},
+					node_rid,
+					mesh_rid,
+					material_index,
+					material_rid,
+					material,
+				)?;
+
+				let binding = match self.bindings.borrow(node_rid, mesh_rid, material_index) {
+					Some(binding) => binding,
+					None => continue,
+				};
+
+				self.render_pipelines.update(
+					&WGPURenderPipelineContext {
+						device: &self.device,
+						format: self.surface_configuration.format,
+						frame: self.frame,
+						output_color_space: &output_color_space,
+						pools: pools,
+						sample_count: self.sample_count,
+					},
+					&draw_key,
+					material_rid,
+					material,
+					binding.borrow_layout(),
+					vertex_layout,
+				)?;
+			}
 		}
+
+		Ok(())
 	}
 
 	fn render_internal(
-		&self,
+		&mut self,
 		pools: &ResourcePools,
 		scene_rid: &ResourceId<Scene>,
-	) {
-		let attribute_pool = pools.borrow::<Attribute>();
-		let geometry_pool = pools.borrow::<Geometry>();
-		let index_pool = pools.borrow::<Index>();
-		let mesh_pool = pools.borrow::<Mesh>();
+	) -> Result<()> {
+		let scene = pools.try_borrow::<Scene>()?
+			.borrow(scene_rid)
+			.ok_or(Error::ResourceNotFound("Scene"))?;
+
+		if !self.has_drawable_size() {
+			return Ok(());
+		}
 
-		// @TODO: Error handling
+		let frame = self.acquire_frame()?;
 
-		let scene = match pools.borrow::<Scene>().borrow(scene_rid) {
-			Some(scene) => scene,
-			None => return,
+		let view = match (&frame, &self.target) {
+			(Some(frame), _) => frame
+				.texture
+				.create_view(&wgpu::TextureViewDescriptor::default()),
+			(None, RenderTarget::Offscreen(texture)) => texture
+				.create_view(&wgpu::TextureViewDescriptor::default()),
+			// Surface is not ready. Skip this frame.
+			(None, RenderTarget::Surface(_)) => return Ok(()),
 		};
 
-		let frame = self.surface
-			.get_current_texture()
-			.expect("Failed to acquire next swap chain texture");
+		let mut encoder = self.device.create_command_encoder(
+			&wgpu::CommandEncoderDescriptor {label: None});
+
+		self.encode_render_pass(&mut encoder, pools, scene, &view);
 
-		let view = frame
-			.texture
-			.create_view(&wgpu::TextureViewDescriptor::default());
+		let captured_frame = if self.capture_requested {
+			Some(match (&frame, &self.target) {
+				(_, RenderTarget::Offscreen(texture)) => {
+					encode_read_back(&self.device, &mut encoder, texture, &self.surface_configuration)
+				},
+				// Copies the presented frame if the surface has been
+				// configured to be copyable (see get_surface_usage())
+				(Some(frame), RenderTarget::Surface(_)) if self.surface_configuration.usage
+					.contains(wgpu::TextureUsages::COPY_SRC) => {
+					encode_read_back(&self.device, &mut encoder, &frame.texture, &self.surface_configuration)
+				},
+				// Otherwise renders the same scene once more into a copyable texture
+				(_, RenderTarget::Surface(_)) => {
+					let texture = create_offscreen_buffer(&self.device, &wgpu::SurfaceConfiguration {
+						usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+						..self.surface_configuration.clone()
+					});
+					self.encode_render_pass(
+						&mut encoder,
+						pools,
+						scene,
+						&texture.create_view(&wgpu::TextureViewDescriptor::default()),
+					);
+					encode_read_back(&self.device, &mut encoder, &texture, &self.surface_configuration)
+				},
+			})
+		} else {
+			None
+		};
+
+		self.queue.submit(Some(encoder.finish()));
+
+		if let Some(frame) = frame {
+			frame.present();
+		}
+
+		if captured_frame.is_some() {
+			self.captured_frame = captured_frame;
+			self.capture_requested = false;
+		}
+
+		Ok(())
+	}
+
+	fn encode_render_pass(
+		&self,
+		encoder: &mut wgpu::CommandEncoder,
+		pools: &ResourcePools,
+		scene: &Scene,
+		view: &wgpu::TextureView,
+	) {
+		let geometry_pool = pools.borrow::<Geometry>();
+		let index_pool = pools.borrow::<Index>();
+		let instanced_mesh_pool = pools.borrow::<InstancedMesh>();
+		let mesh_pool = pools.borrow::<Mesh>();
 
 		let color_view = &self.color_buffer.create_view(&wgpu::TextureViewDescriptor::default());
 		let depth_view = &self.depth_buffer.create_view(&wgpu::TextureViewDescriptor::default());
 
-		let background_color = scene.borrow_background_color();
-
-		let mut encoder = self.device.create_command_encoder(
-			&wgpu::CommandEncoderDescriptor {label: None});
+		// Background color is sRGB. Decoded if the shaders output
+		// linear values, to be written in the same encoding as them.
+		let mut background_color = *scene.borrow_background_color();
+		if let ColorSpace::Linear = self.get_shader_output_color_space() {
+			Color::convert_srgb_to_linear(&mut background_color);
+		}
 
-		{
-			let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-				label: None,
-				color_attachments: &[wgpu::RenderPassColorAttachment {
-					ops: wgpu::Operations {
-						load: wgpu::LoadOp::Clear(wgpu::Color {
-							r: background_color[0] as f64,
-							g: background_color[1] as f64,
-							b: background_color[2] as f64,
-							a: 1.0,
-						}),
-						store: true,
-					},
-					resolve_target: match self.sample_count {
-						1 => None,
-						_ => Some(&view),
-					},
-					view: match self.sample_count {
-						1 => &view,
-						_ => &color_view,
-					},
-				}],
-				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-					depth_ops: Some(wgpu::Operations {
-						load: wgpu::LoadOp::Clear(1.0),
-						store: true,
+		let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: None,
+			color_attachments: &[wgpu::RenderPassColorAttachment {
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color {
+						r: background_color[0] as f64,
+						g: background_color[1] as f64,
+						b: background_color[2] as f64,
+						a: 1.0,
 					}),
-					stencil_ops: None,
-					view: &depth_view,
+					store: true,
+				},
+				resolve_target: match self.sample_count {
+					1 => None,
+					_ => Some(view),
+				},
+				view: match self.sample_count {
+					1 => view,
+					_ => &color_view,
+				},
+			}],
+			depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+				depth_ops: Some(wgpu::Operations {
+					load: wgpu::LoadOp::Clear(1.0),
+					store: true,
 				}),
-			});
+				stencil_ops: None,
+				view: &depth_view,
+			}),
+		});
 
-			for node_rid in scene.collect_nodes(pools).iter() {
-				let mesh = match scene.borrow_assigned_to::<Mesh>(node_rid) {
-					Some(rid) => match mesh_pool.borrow(rid) {
-						Some(mesh) => mesh,
-						None => continue,
-					},
+		for (node_rid, mesh_rid, instanced_mesh_rid) in collect_meshes(pools, scene).iter() {
+			let mesh = match mesh_pool.borrow(mesh_rid) {
+				Some(mesh) => mesh,
+				None => continue,
+			};
+
+			let geometry = match geometry_pool.borrow(mesh.borrow_geometry()) {
+				Some(geometry) => geometry,
+				None => continue,
+			};
+
+			let instances = match instanced_mesh_rid {
+				Some(rid) => match instanced_mesh_pool.borrow(rid) {
+					Some(instanced_mesh) => 0..instanced_mesh.get_count(),
 					None => continue,
-				};
+				},
+				None => 0..1,
+			};
 
-				let geometry = match geometry_pool.borrow(mesh.borrow_geometry()) {
-					Some(geometry) => geometry,
+			// A draw per group, with the material of the group
+			for (material_index, range) in get_draw_ranges(pools, geometry).into_iter() {
+				let pipeline = match self.render_pipelines.borrow(&DrawKey {
+					instanced: *instanced_mesh_rid,
+					material_index: material_index,
+					mesh: *mesh_rid,
+					node: *node_rid,
+				}) {
+					Some(pipeline) => pipeline,
 					None => continue,
 				};
 
-				if let Some(pipeline) = self.render_pipelines.borrow(node_rid) {
-					pass.set_pipeline(&pipeline);
-				} else {
-					continue;
-				}
+				let binding = match self.bindings.borrow(node_rid, mesh_rid, material_index) {
+					Some(binding) => binding,
+					None => continue,
+				};
 
-				// @TODO: Should be programmable
-				if let Some(rid) = geometry.borrow_attribute("position") {
-					if let Some(buffer) = self.attributes.borrow(rid) {
-						pass.set_vertex_buffer(0, buffer.slice(..));
-					}
-				}
-				if let Some(rid) = geometry.borrow_attribute("normal") {
-					if let Some(buffer) = self.attributes.borrow(rid) {
-						pass.set_vertex_buffer(1, buffer.slice(..));
+				// Vertex buffer slots follow the vertex layout the pipeline
+				// has been built with. Skips if any buffer is missing.
+				let vertex_layout = pipeline.borrow_vertex_layout();
+				let mut buffers = Vec::new();
+				for buffer in vertex_layout.borrow_buffers().iter() {
+					let name = vertex_layout.borrow_attributes()[buffer.borrow_attributes()[0]].borrow_name();
+					let buffer = match (buffer.is_instanced(), instanced_mesh_rid) {
+						(true, Some(rid)) => self.instances.borrow(rid),
+						(true, None) => None,
+						(false, _) => match geometry.borrow_attribute(name) {
+							Some(rid) => self.attributes.borrow(rid),
+							None => geometry.borrow_interleaved_attribute(name)
+								.and_then(|attribute| self.interleaved_buffers.borrow(attribute.borrow_buffer())),
+						},
+					};
+					if let Some(buffer) = buffer {
+						buffers.push(buffer);
 					}
 				}
-				if let Some(rid) = geometry.borrow_attribute("uv") {
-					if let Some(buffer) = self.attributes.borrow(rid) {
-						pass.set_vertex_buffer(2, buffer.slice(..));
-					}
+				if buffers.len() != vertex_layout.borrow_buffers().len() {
+					continue;
 				}
 
-				let binding = self.bindings.borrow(node_rid).unwrap();
+				pass.set_pipeline(pipeline.borrow_pipeline());
+				for (slot, buffer) in buffers.iter().enumerate() {
+					pass.set_vertex_buffer(slot as u32, buffer.slice(..));
+				}
 				pass.set_bind_group(0, &binding.borrow_group(), &[]);
 
-				if let Some(rid) = geometry.borrow_index() {
-					if let Some(indices) = index_pool.borrow(rid) {
-						if let Some(buffer) = self.indices.borrow(rid) {
-							pass.set_index_buffer(buffer.slice(..), wgpu::IndexFormat::Uint16);
-							pass.draw_indexed(0..indices.get_count(), 0, 0..1);
-						}
-					}
-				} else {
-					if let Some(rid) = geometry.borrow_attribute("position") {
-						if let Some(positions) = attribute_pool.borrow(rid) {
-							pass.draw(0..positions.get_count(), 0..1);
+				match geometry.borrow_index() {
+					Some(rid) => {
+						if let Some(indices) = index_pool.borrow(rid) {
+							if let Some(buffer) = self.indices.borrow(rid) {
+								pass.set_index_buffer(buffer.slice(..), match indices.get_format() {
+									IndexFormat::Uint16 => wgpu::IndexFormat::Uint16,
+									IndexFormat::Uint32 => wgpu::IndexFormat::Uint32,
+								});
+								pass.draw_indexed(range, 0, instances.clone());
+							}
 						}
-					}
-				}
+					},
+					None => pass.draw(range, instances.clone()),
+				};
 			}
 		}
-
-		self.queue.submit(Some(encoder.finish()));
-		frame.present()
 	}
 
 	pub fn render(
@@ -386,15 +803,173 @@ impl WGPURenderer {
 		pools: &ResourcePools,
 		scene_rid: &ResourceId<Scene>,
 		camera_rid: &ResourceId<PerspectiveCamera>,
-	) {
-		self.update(pools, scene_rid, camera_rid);
-		self.render_internal(pools, scene_rid);
+	) -> Result<()> {
+		self.update(pools, scene_rid, camera_rid)?;
+		self.render_internal(pools, scene_rid)?;
+		if let Some(frames) = self.release_unused_after_frames {
+			self.collect_garbage_internal(pools, frames);
+		}
+		Ok(())
+	}
+
+	// Releases GPU objects of removed resources and of the ones
+	// not used in the last render()
+	pub fn collect_garbage(&mut self, pools: &ResourcePools) -> &mut Self {
+		self.collect_garbage_internal(pools, 0);
+		self
+	}
+
+	fn collect_garbage_internal(&mut self, pools: &ResourcePools, max_unused_frames: u64) {
+		self.attributes.collect_garbage(pools, self.frame, max_unused_frames);
+		self.indices.collect_garbage(pools, self.frame, max_unused_frames);
+		self.instances.collect_garbage(pools, self.frame, max_unused_frames);
+		self.interleaved_buffers.collect_garbage(pools, self.frame, max_unused_frames);
+		self.textures.collect_garbage(pools, self.frame, max_unused_frames);
+		self.samplers.collect_garbage(pools, self.frame, max_unused_frames);
+		self.bindings.collect_garbage(pools, self.frame, max_unused_frames);
+		self.render_pipelines.collect_garbage(pools, self.frame, max_unused_frames);
+		self.vertex_layouts.collect_garbage(pools, self.frame, max_unused_frames);
+	}
+
+	// Reads the last rendered frame back from the offscreen buffer as
+	// tightly packed RGBA8 rows. Returns None if the renderer is not offscreen.
+	pub async fn read_pixels(&self) -> Result<Option<Vec<u8>>> {
+		let texture = match &self.target {
+			RenderTarget::Offscreen(texture) => texture,
+			RenderTarget::Surface(_) => return Ok(None),
+		};
+
+		let mut encoder = self.device.create_command_encoder(
+			&wgpu::CommandEncoderDescriptor {label: None});
+		let read_back = encode_read_back(&self.device, &mut encoder, texture, &self.surface_configuration);
+		self.queue.submit(Some(encoder.finish()));
+
+		Ok(Some(read_back.read(&self.device).await?))
+	}
+
+	// The frame rendered by the next render() call is kept
+	// so that it can be retrieved with capture() or capture_png().
+	pub fn request_capture(&mut self) -> &mut Self {
+		self.capture_requested = true;
+		self
+	}
+
+	// Returns the captured frame as RGBA8 texture whose size is in
+	// physical pixels (size * pixel ratio). Offscreen renderer can
+	// capture the last rendered frame without request_capture().
+	pub async fn capture(&mut self) -> Result<Option<Texture>> {
+		let read_back = match self.captured_frame.take() {
+			Some(read_back) => read_back,
+			None => match &self.target {
+				RenderTarget::Offscreen(texture) => {
+					let mut encoder = self.device.create_command_encoder(
+						&wgpu::CommandEncoderDescriptor {label: None});
+					let read_back = encode_read_back(&self.device, &mut encoder, texture, &self.surface_configuration);
+					self.queue.submit(Some(encoder.finish()));
+					read_back
+				},
+				RenderTarget::Surface(_) => return Ok(None),
+			},
+		};
+
+		let mut pixels = read_back.read(&self.device).await?;
+
+		match read_back.format {
+			wgpu::TextureFormat::Bgra8Unorm |
+			wgpu::TextureFormat::Bgra8UnormSrgb => {
+				for pixel in pixels.chunks_mut(4) {
+					pixel.swap(0, 2);
+				}
+			},
+			_ => {},
+		};
+
+		Ok(Some(Texture::new(
+			read_back.width,
+			read_back.height,
+			match self.output_color_space {
+				ColorSpace::Linear => TextureFormat::Uint8,
+				ColorSpace::SRGB => TextureFormat::Uint8Srgb,
+			},
+			pixels,
+		)))
+	}
+
+	// Encodes the captured frame as PNG. Pixel ratio is recorded as
+	// physical pixel dimensions so that viewers can show it in the logical size.
+	// Returns false if there is no frame to capture.
+	pub async fn capture_png<W: std::io::Write>(&mut self, writer: W) -> Result<bool> {
+		let texture = match self.capture().await? {
+			Some(texture) => texture,
+			None => return Ok(false),
+		};
+
+		let mut encoder = png::Encoder::new(writer, texture.get_width(), texture.get_height());
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+
+		let mut writer = encoder.write_header()?;
+
+		// pHYs chunk: pixels per meter for X and Y, and unit (1 = meter)
+		let pixels_per_meter = ((96.0 * self.pixel_ratio) / 0.0254).round() as u32;
+		let mut phys = Vec::new();
+		phys.extend_from_slice(&pixels_per_meter.to_be_bytes());
+		phys.extend_from_slice(&pixels_per_meter.to_be_bytes());
+		phys.push(1);
+		writer.write_chunk(png::chunk::pHYs, &phys)?;
+
+		writer.write_image_data(texture.borrow_texels())?;
+
+		Ok(true)
+	}
+
+	// Returns None for offscreen renderer, or if the frame should be skipped
+	// because the surface is temporarily unavailable. Lost or outdated
+	// surface is reconfigured and acquired once again.
+	fn acquire_frame(&mut self) -> Result<Option<wgpu::SurfaceTexture>> {
+		let result = match &self.target {
+			RenderTarget::Offscreen(_) => return Ok(None),
+			RenderTarget::Surface(surface) => surface.get_current_texture(),
+		};
+
+		let result = match result {
+			Err(wgpu::SurfaceError::Lost) |
+			Err(wgpu::SurfaceError::Outdated) => {
+				self.update_surface_configuration();
+				match &self.target {
+					RenderTarget::Offscreen(_) => return Ok(None),
+					RenderTarget::Surface(surface) => surface.get_current_texture(),
+				}
+			},
+			_ => result,
+		};
+
+		match result {
+			Ok(frame) => Ok(Some(frame)),
+			Err(wgpu::SurfaceError::OutOfMemory) => Err(Error::Surface(wgpu::SurfaceError::OutOfMemory)),
+			Err(wgpu::SurfaceError::Lost) |
+			Err(wgpu::SurfaceError::Outdated) |
+			Err(wgpu::SurfaceError::Timeout) => Ok(None),
+		}
+	}
+
+	fn has_drawable_size(&self) -> bool {
+		(self.width * self.pixel_ratio) as u32 > 0 &&
+		(self.height * self.pixel_ratio) as u32 > 0
 	}
 
 	fn update_surface_configuration(&mut self) {
 		self.surface_configuration.width = (self.width * self.pixel_ratio) as u32;
 		self.surface_configuration.height = (self.height * self.pixel_ratio) as u32;
-		self.surface.configure(&self.device, &self.surface_configuration);
+		match &mut self.target {
+			RenderTarget::Offscreen(texture) => {
+				texture.destroy();
+				*texture = create_offscreen_buffer(&self.device, &self.surface_configuration);
+			},
+			RenderTarget::Surface(surface) => {
+				surface.configure(&self.device, &self.surface_configuration);
+			},
+		};
 	}
 
 	fn recreate_color_buffer(&mut self) {
@@ -405,6 +980,7 @@ impl WGPURenderer {
 			self.height,
 			self.pixel_ratio,
 			self.sample_count,
+			self.surface_configuration.format,
 		);
 	}
 
@@ -420,12 +996,167 @@ impl WGPURenderer {
 	}
 }
 
+// Meshes assigned to the nodes in the scene with the nodes, and the
+// meshes of instanced meshes with the instanced meshes
+fn collect_meshes(
+	pools: &ResourcePools,
+	scene: &Scene,
+) -> Vec<(ResourceId<Node>, ResourceId<Mesh>, Option<ResourceId<InstancedMesh>>)> {
+	let instanced_mesh_pool = pools.borrow::<InstancedMesh>();
+	let mut meshes = Vec::new();
+	for node_rid in scene.collect_nodes(pools).iter() {
+		for mesh_rid in scene.borrow_all_assigned_to::<Mesh>(node_rid).iter() {
+			meshes.push((*node_rid, *mesh_rid, None));
+		}
+		for rid in scene.borrow_all_assigned_to::<InstancedMesh>(node_rid).iter() {
+			if let Some(instanced_mesh) = instanced_mesh_pool.borrow(rid) {
+				meshes.push((*node_rid, *instanced_mesh.borrow_mesh(), Some(*rid)));
+			}
+		}
+	}
+	meshes
+}
+
+// Material index and range pairs of the geometry to draw
+fn get_draw_ranges(pools: &ResourcePools, geometry: &Geometry) -> Vec<(usize, Range<u32>)> {
+	let count = match geometry.borrow_index() {
+		Some(rid) => pools.borrow::<Index>().borrow(rid).map(|index| index.get_count()),
+		None => get_vertex_count(pools, geometry),
+	};
+	match count {
+		Some(count) => geometry.collect_draw_ranges(count),
+		None => Vec::new(),
+	}
+}
+
+// Of the position attribute
+fn get_vertex_count(pools: &ResourcePools, geometry: &Geometry) -> Option<u32> {
+	match geometry.borrow_attribute("position") {
+		Some(rid) => pools.borrow::<Attribute>()
+			.borrow(rid)
+			.map(|attribute| attribute.get_count()),
+		None => geometry.borrow_interleaved_attribute("position")
+			.and_then(|attribute| pools.borrow::<InterleavedBuffer>().borrow(attribute.borrow_buffer()))
+			.map(|buffer| buffer.get_count()),
+	}
+}
+
+// Surface textures are made copyable so that captures can copy the presented
+// frame. wgpu doesn't expose the surface capabilities yet, so it's only done
+// on the backends whose surfaces support copying in practice. Unsupported
+// usage would be a fatal error on configure().
+fn get_surface_usage(adapter: &wgpu::Adapter) -> wgpu::TextureUsages {
+	match adapter.get_info().backend {
+		wgpu::Backend::Dx12 |
+		wgpu::Backend::Vulkan => wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+		_ => wgpu::TextureUsages::RENDER_ATTACHMENT,
+	}
+}
+
+fn encode_read_back(
+	device: &wgpu::Device,
+	encoder: &mut wgpu::CommandEncoder,
+	texture: &wgpu::Texture,
+	configuration: &wgpu::SurfaceConfiguration,
+) -> ReadBackBuffer {
+	let width = configuration.width;
+	let height = configuration.height;
+	let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+	let padded_bytes_per_row = (width * 4 + align - 1) / align * align;
+
+	let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+		label: None,
+		mapped_at_creation: false,
+		size: (padded_bytes_per_row * height) as u64,
+		usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+	});
+
+	encoder.copy_texture_to_buffer(
+		texture.as_image_copy(),
+		wgpu::ImageCopyBuffer {
+			buffer: &buffer,
+			layout: wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+				rows_per_image: None,
+			},
+		},
+		wgpu::Extent3d {
+			width: width,
+			height: height,
+			depth_or_array_layers: 1,
+		},
+	);
+
+	ReadBackBuffer {
+		buffer: buffer,
+		format: configuration.format,
+		height: height,
+		padded_bytes_per_row: padded_bytes_per_row,
+		width: width,
+	}
+}
+
+// Linear output needs a non-sRGB format not to be encoded on write.
+fn select_format(
+	format: wgpu::TextureFormat,
+	output_color_space: &ColorSpace,
+) -> wgpu::TextureFormat {
+	match output_color_space {
+		ColorSpace::Linear => match format {
+			wgpu::TextureFormat::Bgra8UnormSrgb => wgpu::TextureFormat::Bgra8Unorm,
+			wgpu::TextureFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8Unorm,
+			_ => format,
+		},
+		ColorSpace::SRGB => format,
+	}
+}
+
+async fn request_device(
+	adapter: &wgpu::Adapter,
+	options: &WGPURendererOptions,
+) -> Result<(wgpu::Device, wgpu::Queue)> {
+	Ok(adapter
+		.request_device(
+			&wgpu::DeviceDescriptor {
+				features: options.features & adapter.features(),
+				label: None,
+				limits: match &options.limits {
+					Some(limits) => limits.clone(),
+					None => wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+				},
+			},
+			None,
+		)
+		.await?)
+}
+
+fn create_offscreen_buffer(
+	device: &wgpu::Device,
+	configuration: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+	device.create_texture(&wgpu::TextureDescriptor {
+		label: None,
+		size: wgpu::Extent3d {
+			width: configuration.width,
+			height: configuration.height,
+			depth_or_array_layers: 1,
+		},
+		mip_level_count: 1,
+		sample_count: 1,
+		dimension: wgpu::TextureDimension::D2,
+		format: configuration.format,
+		usage: configuration.usage,
+	})
+}
+
 fn create_color_buffer(
 	device: &wgpu::Device,
 	width: f64,
 	height: f64,
 	pixel_ratio: f64,
 	sample_count: u32,
+	format: wgpu::TextureFormat,
 ) -> wgpu::Texture {
 	device.create_texture(&wgpu::TextureDescriptor {
 		label: None,
@@ -437,7 +1168,7 @@ fn create_color_buffer(
 		mip_level_count: 1,
 		sample_count: sample_count,
 		dimension: wgpu::TextureDimension::D2,
-		format: wgpu::TextureFormat::Bgra8Unorm,
+		format: format,
 		usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
 	})
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::{
 		material::Material,
 		node::node::MaterialNode,
@@ -15,37 +16,64 @@ use crate::{
 	},
 };
 
+struct Entry {
+	last_used: u64,
+	sampler: wgpu::Sampler,
+	serial: u64,
+	version: u64,
+}
+
 pub struct WGPUSamplers {
-	samplers: HashMap<ResourceId<Sampler>, wgpu::Sampler>,
+	next_serial: u64,
+	samplers: HashMap<ResourceId<Sampler>, Entry>,
 }
 
 impl WGPUSamplers {
 	pub fn new() -> Self {
 		WGPUSamplers {
+			next_serial: 0,
 			samplers: HashMap::new(),
 		}
 	}
 
 	pub fn borrow(&self, sampler: &ResourceId<Sampler>) -> Option<&wgpu::Sampler> {
-		self.samplers.get(sampler)
+		self.samplers.get(sampler).map(|entry| &entry.sampler)
+	}
+
+	// Unique number of the current GPU sampler. It changes when the
+	// sampler is recreated so that bind groups know to rebuild.
+	pub fn get_serial(&self, sampler: &ResourceId<Sampler>) -> Option<u64> {
+		self.samplers.get(sampler).map(|entry| entry.serial)
 	}
 
-	// @TODO: Implement correctly
+	// wgpu::Sampler is immutable so that it is recreated
+	// when the sampler has been changed
 	fn update(
 		&mut self,
 		device: &wgpu::Device,
 		pools: &ResourcePools,
 		sampler_rid: &ResourceId<Sampler>,
+		frame: u64,
 	) {
-		if !self.samplers.contains_key(sampler_rid) {
-			if let Some(sampler) = pools.borrow::<Sampler>().borrow(sampler_rid) {
-				let sampler_gpu = create_sampler(
-					device,
-					sampler,
-				);
-				self.samplers.insert(*sampler_rid, sampler_gpu);
+		let sampler = match pools.borrow::<Sampler>().borrow(sampler_rid) {
+			Some(sampler) => sampler,
+			None => return,
+		};
+
+		if let Some(entry) = self.samplers.get_mut(sampler_rid) {
+			entry.last_used = frame;
+			if entry.version == sampler.get_version() {
+				return;
 			}
 		}
+
+		self.samplers.insert(*sampler_rid, Entry {
+			last_used: frame,
+			sampler: create_sampler(device, sampler),
+			serial: self.next_serial,
+			version: sampler.get_version(),
+		});
+		self.next_serial += 1;
 	}
 
 	pub fn update_from_material(
@@ -53,13 +81,29 @@ impl WGPUSamplers {
 		device: &wgpu::Device,
 		pools: &ResourcePools,
 		material: &Material,
-	) {
+		frame: u64,
+	) -> Result<()> {
 		let samplers = material.borrow_samplers(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		);
+		)?;
 		for sampler in samplers.iter() {
-			self.update(device, pools, sampler);
+			self.update(device, pools, sampler, frame);
 		}
+		Ok(())
+	}
+
+	// Releases the samplers of removed samplers and of the ones
+	// not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<Sampler>();
+		self.samplers.retain(|rid, entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
 	}
 }
 
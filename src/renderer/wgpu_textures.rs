@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::{
+	error::error::Result,
 	material::{
 		material::Material,
 		node::node::MaterialNode,
@@ -14,47 +15,101 @@ use crate::{
 	},
 };
 
+struct Entry {
+	format: wgpu::TextureFormat,
+	height: u32,
+	last_used: u64,
+	serial: u64,
+	texture: wgpu::Texture,
+	version: u64,
+	width: u32,
+}
+
 pub struct WGPUTextures {
-	textures: HashMap<ResourceId<Texture>, wgpu::Texture>,
+	next_serial: u64,
+	textures: HashMap<ResourceId<Texture>, Entry>,
 }
 
 impl WGPUTextures {
 	pub fn new() -> WGPUTextures {
 		WGPUTextures {
+			next_serial: 0,
 			textures: HashMap::new(),
 		}
 	}
 
 	pub fn borrow(&self, texture: &ResourceId<Texture>) -> Option<&wgpu::Texture> {
-		self.textures.get(texture)
+		self.textures.get(texture).map(|entry| &entry.texture)
+	}
+
+	// Unique number of the current GPU texture. It changes when the
+	// texture is recreated so that bind groups know to rebuild.
+	pub fn get_serial(&self, texture: &ResourceId<Texture>) -> Option<u64> {
+		self.textures.get(texture).map(|entry| entry.serial)
 	}
 
-	// @TODO: Implement correctly
+	// Creates the texture at first, and re-uploads the texels when the
+	// texture has been changed since. The texture is recreated if
+	// the size or the format differs.
 	fn update(
 		&mut self,
 		device: &wgpu::Device,
 		queue: &wgpu::Queue,
 		pools: &ResourcePools,
 		texture_rid: &ResourceId<Texture>,
+		frame: u64,
 	) {
-		if !self.textures.contains_key(texture_rid) {
-			if let Some(texture) = pools.borrow::<Texture>().borrow(texture_rid) {
-				let texture_gpu = create_texture(
-					device,
-					texture.get_width(),
-					texture.get_height(),
-					get_wgpu_format(texture.borrow_format()),
-				);
+		let texture = match pools.borrow::<Texture>().borrow(texture_rid) {
+			Some(texture) => texture,
+			None => return,
+		};
+
+		let width = texture.get_width();
+		let height = texture.get_height();
+		let format = get_wgpu_format(texture.borrow_format());
+
+		if let Some(entry) = self.textures.get_mut(texture_rid) {
+			entry.last_used = frame;
+			if entry.version == texture.get_version() {
+				return;
+			}
+			if entry.width == width && entry.height == height && entry.format == format {
 				upload_texture(
 					queue,
-					&texture_gpu,
-					texture.get_width(),
-					texture.get_height(),
+					&entry.texture,
+					width,
+					height,
 					bytemuck::cast_slice(texture.borrow_texels()),
 				);
-				self.textures.insert(*texture_rid, texture_gpu);
+				entry.version = texture.get_version();
+				return;
 			}
 		}
+
+		let texture_gpu = create_texture(
+			device,
+			width,
+			height,
+			format,
+		);
+		upload_texture(
+			queue,
+			&texture_gpu,
+			width,
+			height,
+			bytemuck::cast_slice(texture.borrow_texels()),
+		);
+
+		self.textures.insert(*texture_rid, Entry {
+			format: format,
+			height: height,
+			last_used: frame,
+			serial: self.next_serial,
+			texture: texture_gpu,
+			version: texture.get_version(),
+			width: width,
+		});
+		self.next_serial += 1;
 	}
 
 	pub fn update_from_material(
@@ -63,13 +118,29 @@ impl WGPUTextures {
 		queue: &wgpu::Queue,
 		pools: &ResourcePools,
 		material: &Material,
-	) {
+		frame: u64,
+	) -> Result<()> {
 		let textures = material.borrow_textures(
 			pools.borrow::<Box<dyn MaterialNode>>(),
-		);
+		)?;
 		for texture in textures.iter() {
-			self.update(device, queue, pools, texture);
+			self.update(device, queue, pools, texture, frame);
 		}
+		Ok(())
+	}
+
+	// Releases the textures of removed textures and of the ones
+	// not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<Texture>();
+		self.textures.retain(|rid, entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
 	}
 }
 
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use crate::{
+	error::error::Result,
+	geometry::{
+		attribute::Attribute,
+		geometry::Geometry,
+		interleaved_buffer::InterleavedBuffer,
+		vertex_layout::VertexLayout,
+	},
+	resource::resource::{
+		ResourceId,
+		ResourcePools,
+	},
+};
+
+// The vertex layout of a geometry, with or without the instance inputs.
+// The id is unique among the layouts the cache has built and changes
+// only when the layout does, so that draws can compare it rather than
+// the layout.
+pub struct WGPUVertexLayout {
+	id: u64,
+	layout: VertexLayout,
+}
+
+impl WGPUVertexLayout {
+	pub fn get_id(&self) -> u64 {
+		self.id
+	}
+
+	pub fn borrow_layout(&self) -> &VertexLayout {
+		&self.layout
+	}
+}
+
+struct Entry {
+	geometry_version: u64,
+	last_used: u64,
+	layout: WGPUVertexLayout,
+	// Of the attributes and the interleaved buffers of the geometry,
+	// None if removed from the pools
+	source_versions: Vec<Option<u64>>,
+}
+
+// Per geometry and None if not instanced, or whether the instance inputs
+// have colors if instanced
+type Key = (ResourceId<Geometry>, Option<bool>);
+
+pub struct WGPUVertexLayouts {
+	layouts: HashMap<Key, Entry>,
+	next_id: u64,
+}
+
+impl WGPUVertexLayouts {
+	pub fn new() -> Self {
+		WGPUVertexLayouts {
+			layouts: HashMap::new(),
+			next_id: 0,
+		}
+	}
+
+	pub fn borrow(
+		&self,
+		geometry: &ResourceId<Geometry>,
+		instance_color: Option<bool>,
+	) -> Option<&WGPUVertexLayout> {
+		self.layouts.get(&(*geometry, instance_color)).map(|entry| &entry.layout)
+	}
+
+	// Builds the layout at first, and rebuilds it when the geometry, its
+	// attributes or interleaved buffers have been changed since. The layout
+	// is fitted in the device limits. Fails if it doesn't fit.
+	pub fn update(
+		&mut self,
+		pools: &ResourcePools,
+		geometry_rid: &ResourceId<Geometry>,
+		instance_color: Option<bool>,
+		limits: &wgpu::Limits,
+		frame: u64,
+	) -> Result<()> {
+		let geometry = match pools.borrow::<Geometry>().borrow(geometry_rid) {
+			Some(geometry) => geometry,
+			None => return Ok(()),
+		};
+
+		let key = (*geometry_rid, instance_color);
+		let source_versions = collect_source_versions(pools, geometry);
+
+		if let Some(entry) = self.layouts.get_mut(&key) {
+			entry.last_used = frame;
+			if entry.geometry_version == geometry.get_version() &&
+				entry.source_versions == source_versions {
+				return Ok(());
+			}
+		}
+
+		let mut layout = VertexLayout::from_geometry(geometry, pools);
+		if let Some(color) = instance_color {
+			layout.add_instance_attributes(color);
+		}
+		// Instance inputs count too
+		layout.fit_limits(limits.max_vertex_buffers, limits.max_vertex_attributes)?;
+
+		// Data edits don't change the layout in most cases
+		if let Some(entry) = self.layouts.get_mut(&key) {
+			if entry.layout.layout == layout {
+				entry.geometry_version = geometry.get_version();
+				entry.source_versions = source_versions;
+				return Ok(());
+			}
+		}
+
+		let id = self.next_id;
+		self.next_id += 1;
+
+		self.layouts.insert(key, Entry {
+			geometry_version: geometry.get_version(),
+			last_used: frame,
+			layout: WGPUVertexLayout {
+				id: id,
+				layout: layout,
+			},
+			source_versions: source_versions,
+		});
+
+		Ok(())
+	}
+
+	// Releases the layouts of removed geometries and of the ones
+	// not used in the last max_unused_frames frames
+	pub fn collect_garbage(
+		&mut self,
+		pools: &ResourcePools,
+		frame: u64,
+		max_unused_frames: u64,
+	) {
+		let pool = pools.borrow::<Geometry>();
+		self.layouts.retain(|(rid, _), entry| {
+			pool.contains(rid) && frame - entry.last_used <= max_unused_frames
+		});
+	}
+}
+
+// The order is stable while the geometry version is unchanged
+fn collect_source_versions(pools: &ResourcePools, geometry: &Geometry) -> Vec<Option<u64>> {
+	let attribute_pool = pools.borrow::<Attribute>();
+	let interleaved_buffer_pool = pools.borrow::<InterleavedBuffer>();
+	geometry.borrow_attributes()
+		.values()
+		.map(|rid| attribute_pool.borrow(rid).map(|attribute| attribute.get_version()))
+		.chain(geometry.borrow_interleaved_attributes()
+			.values()
+			.map(|attribute| interleaved_buffer_pool
+				.borrow(attribute.borrow_buffer())
+				.map(|buffer| buffer.get_version())))
+		.collect()
+}
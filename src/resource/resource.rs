@@ -9,14 +9,18 @@ use std::{
 		Hasher,
 	},
 	marker::PhantomData,
-	mem::transmute,
 };
 
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	geometry::{
 		attribute::Attribute,
 		geometry::Geometry,
 		index::Index,
+		interleaved_buffer::InterleavedBuffer,
 	},
 	material::{
 		material::Material,
@@ -24,6 +28,7 @@ use crate::{
 	},
 	scene::{
 		camera::PerspectiveCamera,
+		instanced_mesh::InstancedMesh,
 		mesh::Mesh,
 		node::Node,
 		scene::Scene,
@@ -34,16 +39,27 @@ use crate::{
 	},
 };
 
-trait ResourcePoolTrait {
+// Pools are Send + Sync so that ResourcePools can be moved to or shared
+// with other threads, for example behind Arc<RwLock<ResourcePools>>.
+trait ResourcePoolTrait: Send + Sync {
 	fn as_any(&self) -> &dyn Any;
 	fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
+// A slot is reused after its resource is removed. The generation is
+// incremented on removal so that stale ResourceIds don't alias
+// the new resource in the slot.
+struct Slot<T> {
+	generation: u32,
+	resource: Option<T>,
+}
+
 pub struct ResourcePool<T> {
-	resources: Vec<T>,
+	free_slots: Vec<usize>,
+	slots: Vec<Slot<T>>,
 }
 
-impl<T: 'static> ResourcePoolTrait for ResourcePool<T> {
+impl<T: Send + Sync + 'static> ResourcePoolTrait for ResourcePool<T> {
 	fn as_any(&self) -> &dyn Any {
 		self
 	}
@@ -53,55 +69,105 @@ impl<T: 'static> ResourcePoolTrait for ResourcePool<T> {
 	}	
 }
 
-fn cast_pool<T: 'static>(pool: &dyn ResourcePoolTrait) -> &ResourcePool<T> {
+fn cast_pool<T: Send + Sync + 'static>(pool: &dyn ResourcePoolTrait) -> &ResourcePool<T> {
 	pool
 		.as_any()
 		.downcast_ref::<ResourcePool<T>>()
 		.unwrap()
 }
 
-fn cast_pool_mut<T: 'static>(pool: &mut dyn ResourcePoolTrait) -> &mut ResourcePool<T> {
+fn cast_pool_mut<T: Send + Sync + 'static>(pool: &mut dyn ResourcePoolTrait) -> &mut ResourcePool<T> {
 	pool
 		.as_any_mut()
 		.downcast_mut::<ResourcePool<T>>()
 		.unwrap()
 }
 
-// @TODO: Write comment
-fn cast_pool_mut_unsafe<T: 'static>(pool: &Box<dyn ResourcePoolTrait>) -> &mut ResourcePool<T> {
-	let ptr = cast_pool(pool.as_ref())
-		as *const ResourcePool<T> as *mut ResourcePool<T>;
-	unsafe { transmute(ptr) }
-}
-
-impl<T: 'static> ResourcePool<T> {
+impl<T: Send + Sync + 'static> ResourcePool<T> {
 	pub fn new() -> Self {
 		ResourcePool {
-			resources: Vec::new(),
+			free_slots: Vec::new(),
+			slots: Vec::new(),
 		}
 	}
 
 	pub fn add(&mut self, resource: T) -> ResourceId<T> {
-		let id = self.resources.len();
-		self.resources.push(resource);
-		ResourceId::new(id)
+		match self.free_slots.pop() {
+			Some(id) => {
+				let slot = &mut self.slots[id];
+				slot.resource = Some(resource);
+				ResourceId::new(id, slot.generation)
+			},
+			None => {
+				let id = self.slots.len();
+				self.slots.push(Slot {
+					generation: 0,
+					resource: Some(resource),
+				});
+				ResourceId::new(id, 0)
+			},
+		}
+	}
+
+	// Returns the removed resource, or None if the id is stale.
+	// Other resources referring to the removed one are not updated.
+	pub fn remove(&mut self, r_id: &ResourceId<T>) -> Option<T> {
+		let slot = match self.slots.get_mut(r_id.id) {
+			Some(slot) if slot.generation == r_id.generation => slot,
+			_ => return None,
+		};
+		let resource = slot.resource.take();
+		if resource.is_some() {
+			slot.generation = slot.generation.wrapping_add(1);
+			self.free_slots.push(r_id.id);
+		}
+		resource
 	}
 
 	pub fn borrow(&self, r_id: &ResourceId<T>) -> Option<&T> {
-		if r_id.id < self.resources.len() {
-			Some(&self.resources[r_id.id])
-		} else {
-			None
+		match self.slots.get(r_id.id) {
+			Some(slot) if slot.generation == r_id.generation => slot.resource.as_ref(),
+			_ => None,
 		}
 	}
 
 	pub fn borrow_mut(&mut self, r_id: &ResourceId<T>) -> Option<&mut T> {
-		if r_id.id < self.resources.len() {
-			Some(&mut self.resources[r_id.id])
-		} else {
-			None
+		match self.slots.get_mut(r_id.id) {
+			Some(slot) if slot.generation == r_id.generation => slot.resource.as_mut(),
+			_ => None,
 		}
 	}
+
+	pub fn contains(&self, r_id: &ResourceId<T>) -> bool {
+		self.borrow(r_id).is_some()
+	}
+
+	// The number of live resources
+	pub fn len(&self) -> usize {
+		self.slots.len() - self.free_slots.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	// Iterates over live resources
+	pub fn iter(&self) -> impl Iterator<Item = (ResourceId<T>, &T)> {
+		self.slots.iter().enumerate().filter_map(|(id, slot)| {
+			slot.resource.as_ref().map(|resource| {
+				(ResourceId::new(id, slot.generation), resource)
+			})
+		})
+	}
+
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = (ResourceId<T>, &mut T)> {
+		self.slots.iter_mut().enumerate().filter_map(|(id, slot)| {
+			let generation = slot.generation;
+			slot.resource.as_mut().map(|resource| {
+				(ResourceId::new(id, generation), resource)
+			})
+		})
+	}
 }
 
 pub struct ResourcePools {
@@ -114,6 +180,8 @@ impl ResourcePools {
 		Self::add::<Attribute>(&mut pools);
 		Self::add::<Geometry>(&mut pools);
 		Self::add::<Index>(&mut pools);
+		Self::add::<InstancedMesh>(&mut pools);
+		Self::add::<InterleavedBuffer>(&mut pools);
 		Self::add::<Material>(&mut pools);
 		Self::add::<Box<dyn MaterialNode>>(&mut pools);
 		Self::add::<Mesh>(&mut pools);
@@ -128,53 +196,74 @@ impl ResourcePools {
 		}
 	}
 
-	fn add<T: 'static>(pools: &mut HashMap<TypeId, Box<dyn ResourcePoolTrait>>) {
+	fn add<T: Send + Sync + 'static>(pools: &mut HashMap<TypeId, Box<dyn ResourcePoolTrait>>) {
 		pools.insert(TypeId::of::<T>(), Box::new(ResourcePool::<T>::new()));
 	}
 
-	pub fn borrow<T: 'static>(&self) -> &ResourcePool<T> {
-		if let Some(pool) = self.pools.get(&TypeId::of::<T>()) {
-			cast_pool(pool.as_ref())
-		} else {
-			// @TODO: Proper error handling
-			// @TODO: Trait bound
-			panic!("Unknown Type");
+	// Adds a pool for user type T, like lights or game data, so that
+	// T can be used with borrow() and assigned to nodes with Scene::assign().
+	// Registering a type twice keeps the existing pool.
+	pub fn register<T: Send + Sync + 'static>(&mut self) -> &mut Self {
+		if !self.is_registered::<T>() {
+			Self::add::<T>(&mut self.pools);
 		}
+		self
+	}
+
+	pub fn is_registered<T: 'static>(&self) -> bool {
+		self.pools.contains_key(&TypeId::of::<T>())
 	}
 
-	pub fn borrow_mut<T: 'static>(&mut self) -> &mut ResourcePool<T> {
-		if let Some(pool) = self.pools.get_mut(&TypeId::of::<T>()) {
-			cast_pool_mut(pool.as_mut())
-		} else {
-			// @TODO: Proper error handling
-			panic!("Unknown Type");
+	// Panics if T is not a registered type. Use try_borrow() to handle it.
+	pub fn borrow<T: Send + Sync + 'static>(&self) -> &ResourcePool<T> {
+		match self.try_borrow::<T>() {
+			Ok(pool) => pool,
+			Err(e) => panic!("{}", e),
 		}
 	}
 
-	// @TODO: Write comment
-	pub fn borrow_mut_unsafe<T: 'static>(&self) -> &mut ResourcePool<T> {
-		if let Some(pool) = self.pools.get(&TypeId::of::<T>()) {
-			cast_pool_mut_unsafe(pool)
-		} else {
-			// @TODO: Proper error handling
-			// @TODO: Trait bound
-			panic!("Unknown Type");
+	pub fn borrow_mut<T: Send + Sync + 'static>(&mut self) -> &mut ResourcePool<T> {
+		match self.try_borrow_mut::<T>() {
+			Ok(pool) => pool,
+			Err(e) => panic!("{}", e),
+		}
+	}
+
+	// @TODO: Trait bound
+	pub fn try_borrow<T: Send + Sync + 'static>(&self) -> Result<&ResourcePool<T>> {
+		match self.pools.get(&TypeId::of::<T>()) {
+			Some(pool) => Ok(cast_pool(pool.as_ref())),
+			None => Err(Error::UnknownResourceType(std::any::type_name::<T>())),
+		}
+	}
+
+	pub fn try_borrow_mut<T: Send + Sync + 'static>(&mut self) -> Result<&mut ResourcePool<T>> {
+		match self.pools.get_mut(&TypeId::of::<T>()) {
+			Some(pool) => Ok(cast_pool_mut(pool.as_mut())),
+			None => Err(Error::UnknownResourceType(std::any::type_name::<T>())),
 		}
 	}
 }
 
 pub struct ResourceId<T> {
 	pub id: usize,
-	_phantom: PhantomData<T>,
+	generation: u32,
+	// fn() -> T so that ids are Send + Sync regardless of T
+	_phantom: PhantomData<fn() -> T>,
 }
 
 impl<T> ResourceId<T> {
-	fn new(id: usize) -> Self {
+	fn new(id: usize, generation: u32) -> Self {
 		ResourceId {
 			id: id,
+			generation: generation,
 			_phantom: PhantomData
 		}
 	}
+
+	pub fn get_generation(&self) -> u32 {
+		self.generation
+	}
 }
 
 impl<T> Copy for ResourceId<T> {
@@ -189,12 +278,13 @@ impl<T> Clone for ResourceId<T> {
 impl<T> Hash for ResourceId<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state);
+        self.generation.hash(state);
     }
 }
 
 impl<T> PartialEq for ResourceId<T> {
 	fn eq(&self, other: &Self) -> bool {
-		self.id == other.id
+		self.id == other.id && self.generation == other.generation
 	}
 }
 
@@ -0,0 +1,94 @@
+use crate::{
+	math::matrix4::Matrix4,
+	resource::resource::ResourceId,
+	scene::mesh::Mesh,
+};
+
+// Draws the mesh count times in one draw call. Instance matrices are
+// applied before the matrix of the node the instanced mesh is assigned
+// to. Instance colors, if set, are multiplied with the material color.
+// Normals are transformed with the upper 3x3 of the instance matrix, so
+// non-uniform scales skew the lighting.
+pub struct InstancedMesh {
+	colors: Option<Vec<[f32; 3]>>,
+	matrices: Vec<[f32; 16]>,
+	mesh: ResourceId<Mesh>,
+	version: u64,
+}
+
+impl InstancedMesh {
+	// Instance matrices are identity
+	pub fn new(mesh: ResourceId<Mesh>, count: u32) -> Self {
+		InstancedMesh {
+			colors: None,
+			matrices: vec![Matrix4::create(); count as usize],
+			mesh: mesh,
+			version: 0,
+		}
+	}
+
+	pub fn borrow_mesh(&self) -> &ResourceId<Mesh> {
+		&self.mesh
+	}
+
+	pub fn set_mesh(&mut self, mesh: ResourceId<Mesh>) -> &mut Self {
+		self.mesh = mesh;
+		self.version += 1;
+		self
+	}
+
+	pub fn get_count(&self) -> u32 {
+		self.matrices.len() as u32
+	}
+
+	// New instances have identity matrices and white colors
+	pub fn set_count(&mut self, count: u32) -> &mut Self {
+		self.matrices.resize(count as usize, Matrix4::create());
+		if let Some(colors) = self.colors.as_mut() {
+			colors.resize(count as usize, [1.0; 3]);
+		}
+		self.version += 1;
+		self
+	}
+
+	pub fn borrow_matrices(&self) -> &Vec<[f32; 16]> {
+		&self.matrices
+	}
+
+	pub fn borrow_matrices_mut(&mut self) -> &mut Vec<[f32; 16]> {
+		self.version += 1;
+		&mut self.matrices
+	}
+
+	// Panics if index is out of the count
+	pub fn set_matrix_at(&mut self, index: u32, matrix: &[f32; 16]) -> &mut Self {
+		Matrix4::copy(&mut self.matrices[index as usize], matrix);
+		self.version += 1;
+		self
+	}
+
+	pub fn borrow_colors(&self) -> Option<&Vec<[f32; 3]>> {
+		self.colors.as_ref()
+	}
+
+	// Colors of all the instances are white until set.
+	// Panics if index is out of the count.
+	pub fn set_color_at(&mut self, index: u32, color: &[f32; 3]) -> &mut Self {
+		let count = self.matrices.len();
+		self.colors.get_or_insert_with(|| vec![[1.0; 3]; count])[index as usize] = *color;
+		self.version += 1;
+		self
+	}
+
+	pub fn remove_colors(&mut self) -> &mut Self {
+		self.colors = None;
+		self.version += 1;
+		self
+	}
+
+	// Incremented whenever the instance data can be changed so that
+	// the renderer knows when to re-upload it
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+}
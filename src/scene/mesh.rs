@@ -1,4 +1,8 @@
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	geometry::geometry::Geometry,
 	material::material::Material,
 	resource::resource::ResourceId,
@@ -7,7 +11,10 @@ use crate::{
 // @TODO: Support shared geometry and material
 pub struct Mesh {
 	geometry: ResourceId<Geometry>,
-	material: ResourceId<Material>,
+	// Indexed by the material index of geometry groups.
+	// The first one is used if the geometry has no groups.
+	// Never empty.
+	materials: Vec<ResourceId<Material>>,
 }
 
 impl Mesh {
@@ -17,15 +24,58 @@ impl Mesh {
 	) -> Self {
 		Mesh {
 			geometry: geometry,
-			material: material,
+			materials: vec![material],
 		}
 	}
 
+	// Fails if materials is empty
+	pub fn new_with_materials(
+		geometry: ResourceId<Geometry>,
+		materials: Vec<ResourceId<Material>>,
+	) -> Result<Self> {
+		if materials.is_empty() {
+			return Err(Error::NoMaterial);
+		}
+		Ok(Mesh {
+			geometry: geometry,
+			materials: materials,
+		})
+	}
+
 	pub fn borrow_geometry(&self) -> &ResourceId<Geometry> {
 		&self.geometry
 	}
 
+	// The first material
 	pub fn borrow_material(&self) -> &ResourceId<Material> {
-		&self.material
+		&self.materials[0]
+	}
+
+	pub fn borrow_material_at(&self, index: usize) -> Option<&ResourceId<Material>> {
+		self.materials.get(index)
+	}
+
+	pub fn borrow_materials(&self) -> &Vec<ResourceId<Material>> {
+		&self.materials
+	}
+
+	pub fn set_geometry(&mut self, geometry: ResourceId<Geometry>) -> &mut Self {
+		self.geometry = geometry;
+		self
+	}
+
+	// Replaces all the materials with one
+	pub fn set_material(&mut self, material: ResourceId<Material>) -> &mut Self {
+		self.materials = vec![material];
+		self
+	}
+
+	// Fails if materials is empty. The current ones are kept then.
+	pub fn set_materials(&mut self, materials: Vec<ResourceId<Material>>) -> Result<&mut Self> {
+		if materials.is_empty() {
+			return Err(Error::NoMaterial);
+		}
+		self.materials = materials;
+		Ok(self)
 	}
 }
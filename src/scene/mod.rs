@@ -1,4 +1,5 @@
 pub mod camera;
+pub mod instanced_mesh;
 pub mod mesh;
 pub mod node;
 pub mod scene;
@@ -1,4 +1,8 @@
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	math::{
 		euler::Euler,
 		matrix4::Matrix4,
@@ -11,6 +15,13 @@ use crate::{
 	},
 };
 
+// Which of rotation and quaternion was set last and
+// update_matrix() should take the rotation from
+enum RotationSource {
+	Euler,
+	Quaternion,
+}
+
 pub struct Node {
 	children: Vec<ResourceId<Node>>,
 	matrix: [f32; 16],
@@ -18,6 +29,7 @@ pub struct Node {
 	position: [f32; 3],
 	quaternion: [f32; 4],
 	rotation: [f32; 3],
+	rotation_source: RotationSource,
 	scale: [f32; 3],
 	world_matrix: [f32; 16],
 }
@@ -31,6 +43,7 @@ impl Node {
 			position: Vector3::create(),
 			quaternion: Quaternion::create(),
 			rotation: Euler::create(),
+			rotation_source: RotationSource::Euler,
 			scale: *Vector3::set(&mut Vector3::create(), 1.0, 1.0, 1.0),
 			world_matrix: Matrix4::create(),
 		}
@@ -57,9 +70,24 @@ impl Node {
 	}
 
 	pub fn borrow_rotation_mut(&mut self) -> &mut [f32; 3] {
+		self.rotation_source = RotationSource::Euler;
 		&mut self.rotation
 	}
 
+	pub fn borrow_quaternion(&self) -> &[f32; 4] {
+		&self.quaternion
+	}
+
+	// The quaternion is used as is by update_matrix() until
+	// the rotation is borrowed mutably. Euler conversion
+	// can't represent every quaternion precisely.
+	pub fn set_quaternion(&mut self, quaternion: &[f32; 4]) -> &mut Self {
+		self.quaternion.copy_from_slice(quaternion);
+		Euler::set_from_quaternion(&mut self.rotation, &self.quaternion);
+		self.rotation_source = RotationSource::Quaternion;
+		self
+	}
+
 	pub fn borrow_scale(&self) -> &[f32; 3] {
 		&self.scale
 	}
@@ -80,6 +108,7 @@ impl Node {
 		Matrix4::copy(&mut self.matrix, matrix);
 		Matrix4::decompose(&mut self.position, &mut self.quaternion, &mut self.scale, &self.matrix);
 		Euler::set_from_quaternion(&mut self.rotation, &self.quaternion);
+		self.rotation_source = RotationSource::Quaternion;
 		self
 	}
 
@@ -89,11 +118,15 @@ impl Node {
 	}
 
 	pub fn update_matrix(&mut self) -> &mut Self {
-		Quaternion::set_from_euler(&mut self.quaternion, &self.rotation);
+		if let RotationSource::Euler = self.rotation_source {
+			Quaternion::set_from_euler(&mut self.quaternion, &self.rotation);
+		}
 		Matrix4::compose(&mut self.matrix, &self.position, &self.quaternion, &self.scale);
 		self
 	}
 
+	// Nodes removed from the pool are skipped with their descendants.
+	// A removed parent is treated as no parent.
 	// @TODO: Optimize
 	pub fn update_matrices(
 		&mut self,
@@ -101,12 +134,14 @@ impl Node {
 	) {
 		self.update_matrix();
 
-		if let Some(parent) = self.borrow_parent() {
-			let parent_matrix = pool.borrow(parent).unwrap().borrow_world_matrix();
-			Matrix4::multiply(&mut self.world_matrix, parent_matrix, &self.matrix);
-		} else {
-			Matrix4::copy(&mut self.world_matrix, &self.matrix);
-		}
+		match self.borrow_parent().and_then(|parent| pool.borrow(parent)) {
+			Some(parent) => {
+				Matrix4::multiply(&mut self.world_matrix, parent.borrow_world_matrix(), &self.matrix);
+			},
+			None => {
+				Matrix4::copy(&mut self.world_matrix, &self.matrix);
+			},
+		};
 
 		let mut stack = Vec::new();
 
@@ -115,15 +150,17 @@ impl Node {
 		}
 
 		while let Some(rid) = stack.pop() {
-			let parent_matrix = {
-				let mut matrix = Matrix4::create();
-				let node = pool.borrow_mut(&rid).unwrap();
-				let parent = node.borrow_parent().cloned().unwrap();
-				Matrix4::copy(&mut matrix, pool.borrow(&parent).unwrap().borrow_world_matrix());
-				matrix
+			let parent_matrix = match pool.borrow(&rid)
+				.and_then(|node| node.borrow_parent())
+				.and_then(|parent| pool.borrow(parent)) {
+				Some(parent) => *parent.borrow_world_matrix(),
+				None => continue,
 			};
 
-			let node = pool.borrow_mut(&rid).unwrap();
+			let node = match pool.borrow_mut(&rid) {
+				Some(node) => node,
+				None => continue,
+			};
 			let mut matrix = Matrix4::create();
 			Matrix4::multiply(&mut matrix, &parent_matrix, &node.borrow_matrix());
 			node.set_world_matrix(&matrix);
@@ -139,6 +176,8 @@ pub struct NodeExecutor {
 }
 
 impl NodeExecutor {
+	// Nodes removed from the pool are skipped with their descendants.
+	// A removed parent is treated as no parent.
 	pub fn update_matrices(
 		pool: &mut ResourcePool<Node>,
 		root: &ResourceId<Node>,
@@ -147,19 +186,20 @@ impl NodeExecutor {
 		stack.push(*root);
 
 		while let Some(rid) = stack.pop() {
-			let node = pool.borrow_mut(&rid).unwrap();
-			node.update_matrix();
+			let parent_matrix = match pool.borrow(&rid) {
+				Some(node) => match node.borrow_parent().and_then(|parent| pool.borrow(parent)) {
+					Some(parent) => *parent.borrow_world_matrix(),
+					None => Matrix4::create(),
+				},
+				None => continue,
+			};
 
-			let parent_matrix = {
-				let mut matrix = Matrix4::create();
-				let node = pool.borrow_mut(&rid).unwrap();
-				if let Some(parent) = node.borrow_parent().cloned() {
-					Matrix4::copy(&mut matrix, pool.borrow(&parent).unwrap().borrow_world_matrix());
-				}
-				matrix
+			let node = match pool.borrow_mut(&rid) {
+				Some(node) => node,
+				None => continue,
 			};
+			node.update_matrix();
 
-			let node = pool.borrow_mut(&rid).unwrap();
 			let mut matrix = Matrix4::create();
 			Matrix4::multiply(&mut matrix, &parent_matrix, &node.borrow_matrix());
 			node.set_world_matrix(&matrix);
@@ -170,21 +210,150 @@ impl NodeExecutor {
 		}
 	}
 
+	// Nodes removed from the pool are skipped with their descendants
 	pub fn collect_nodes(
 		pool: &ResourcePool<Node>,
 		root: &ResourceId<Node>,
 		nodes: &mut Vec<ResourceId<Node>>,
 	) {
+		if !pool.contains(root) {
+			return;
+		}
+
 		let mut stack = Vec::new();
 		stack.push(*root);
 		nodes.push(*root);
 
 		while let Some(rid) = stack.pop() {
-			let node = pool.borrow(&rid).unwrap();
+			let node = match pool.borrow(&rid) {
+				Some(node) => node,
+				None => continue,
+			};
 			for child in node.children.iter() {
-				stack.push(*child);
-				nodes.push(*child);
+				if pool.contains(child) {
+					stack.push(*child);
+					nodes.push(*child);
+				}
 			}
 		}
 	}
+
+	// Appends child to parent's children, detaching it from
+	// its current parent first. The local transform is kept.
+	pub fn add_child(
+		pool: &mut ResourcePool<Node>,
+		parent: &ResourceId<Node>,
+		child: &ResourceId<Node>,
+	) -> Result<()> {
+		Self::set_parent(pool, child, Some(parent), false)
+	}
+
+	// Does nothing if child is not a child of parent
+	pub fn remove_child(
+		pool: &mut ResourcePool<Node>,
+		parent: &ResourceId<Node>,
+		child: &ResourceId<Node>,
+	) -> Result<()> {
+		let is_child = pool.borrow(child)
+			.ok_or(Error::ResourceNotFound("Node"))?
+			.borrow_parent() == Some(parent);
+		match is_child {
+			true => Self::set_parent(pool, child, None, false),
+			false => Ok(()),
+		}
+	}
+
+	// Moves node under parent, or makes it a root with None. Both sides
+	// are kept consistent. Fails if parent is node or its descendant.
+	// With keep_world_transform the local transform is updated so that
+	// the node stays where it is in the world.
+	pub fn set_parent(
+		pool: &mut ResourcePool<Node>,
+		node: &ResourceId<Node>,
+		parent: Option<&ResourceId<Node>>,
+		keep_world_transform: bool,
+	) -> Result<()> {
+		if !pool.contains(node) {
+			return Err(Error::ResourceNotFound("Node"));
+		}
+
+		if let Some(parent) = parent {
+			if !pool.contains(parent) {
+				return Err(Error::ResourceNotFound("Node"));
+			}
+			// Walks up from the new parent
+			let mut ancestor = Some(*parent);
+			while let Some(rid) = ancestor {
+				if rid == *node {
+					return Err(Error::HierarchyCycle);
+				}
+				ancestor = pool.borrow(&rid).and_then(|node| node.borrow_parent().cloned());
+			}
+		}
+
+		let matrix = if keep_world_transform {
+			let mut matrix = Self::compute_world_matrix(pool, node);
+			if let Some(parent) = parent {
+				let mut parent_matrix_inverse = Self::compute_world_matrix(pool, parent);
+				Matrix4::invert(&mut parent_matrix_inverse);
+				let world_matrix = matrix;
+				Matrix4::multiply(&mut matrix, &parent_matrix_inverse, &world_matrix);
+			}
+			Some(matrix)
+		} else {
+			None
+		};
+
+		// The old parent may have been removed from the pool
+		if let Some(old_parent) = pool.borrow(node).and_then(|node| node.borrow_parent().cloned()) {
+			if let Some(old_parent) = pool.borrow_mut(&old_parent) {
+				old_parent.children.retain(|child| child != node);
+			}
+		}
+
+		if let Some(new_parent) = parent.and_then(|parent| pool.borrow_mut(parent)) {
+			new_parent.children.push(*node);
+		}
+
+		if let Some(node) = pool.borrow_mut(node) {
+			node.parent = parent.cloned();
+			if let Some(matrix) = matrix {
+				node.set_matrix(&matrix);
+			}
+		}
+
+		Ok(())
+	}
+
+	// Computed from the current position, rotation and scale of
+	// the node and its ancestors rather than the world matrix
+	// that may not have been updated yet. Stops at a removed ancestor.
+	fn compute_world_matrix(
+		pool: &ResourcePool<Node>,
+		rid: &ResourceId<Node>,
+	) -> [f32; 16] {
+		let mut world_matrix = Matrix4::create();
+		let mut ancestor = Some(*rid);
+		while let Some(rid) = ancestor {
+			let node = match pool.borrow(&rid) {
+				Some(node) => node,
+				None => break,
+			};
+			let mut quaternion = Quaternion::create();
+			match node.rotation_source {
+				RotationSource::Euler => {
+					Quaternion::set_from_euler(&mut quaternion, &node.rotation);
+				},
+				RotationSource::Quaternion => {
+					quaternion.copy_from_slice(&node.quaternion);
+				},
+			};
+			let mut matrix = Matrix4::create();
+			Matrix4::compose(&mut matrix, &node.position, &quaternion, &node.scale);
+			let child_matrix = world_matrix;
+			Matrix4::multiply(&mut world_matrix, &matrix, &child_matrix);
+			ancestor = node.parent;
+		}
+		world_matrix
+	}
 }
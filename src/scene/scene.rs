@@ -7,14 +7,17 @@ use std::{
 };
 
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	math::color::Color,
 	resource::resource::{
 		ResourceId,
+		ResourcePool,
 		ResourcePools,
 	},
 	scene::{
-		camera::PerspectiveCamera,
-		mesh::Mesh,
 		node::{
 			Node,
 			NodeExecutor,
@@ -22,14 +25,14 @@ use crate::{
 	},
 };
 
-
-trait ResourceLinksTrait {
+trait ResourceLinksTrait: Send + Sync {
 	fn as_any(&self) -> &dyn Any;
 	fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
+// A resource can be linked to more than one resource of another type
 pub struct ResourceLinks<T1, T2> {
-	links: HashMap<ResourceId<T1>, ResourceId<T2>>,
+	links: HashMap<ResourceId<T1>, Vec<ResourceId<T2>>>,
 }
 
 impl<T1: 'static, T2: 'static> ResourceLinksTrait for ResourceLinks<T1, T2> {
@@ -53,12 +56,48 @@ impl<T1: 'static, T2: 'static> ResourceLinks<T1, T2> {
 		self.links.contains_key(rid_from)
 	}
 
+	// Does nothing if already linked
 	pub fn add(&mut self, rid_from: &ResourceId<T1>, rid_to: &ResourceId<T2>) {
-		self.links.insert(*rid_from, *rid_to);
+		let links = self.links.entry(*rid_from).or_insert_with(Vec::new);
+		if !links.contains(rid_to) {
+			links.push(*rid_to);
+		}
 	}
 
+	// Replaces all the links from rid_from
+	pub fn set(&mut self, rid_from: &ResourceId<T1>, rid_to: &ResourceId<T2>) {
+		self.links.insert(*rid_from, vec![*rid_to]);
+	}
+
+	// The first linked one
 	pub fn borrow(&self, rid_from: &ResourceId<T1>) -> Option<&ResourceId<T2>> {
-		self.links.get(rid_from)
+		self.links.get(rid_from)?.first()
+	}
+
+	pub fn borrow_all(&self, rid_from: &ResourceId<T1>) -> &[ResourceId<T2>] {
+		match self.links.get(rid_from) {
+			Some(links) => links,
+			None => &[],
+		}
+	}
+
+	pub fn remove(&mut self, rid_from: &ResourceId<T1>) -> Vec<ResourceId<T2>> {
+		self.links.remove(rid_from).unwrap_or_default()
+	}
+
+	// Returns false if not linked
+	pub fn remove_link(&mut self, rid_from: &ResourceId<T1>, rid_to: &ResourceId<T2>) -> bool {
+		let links = match self.links.get_mut(rid_from) {
+			Some(links) => links,
+			None => return false,
+		};
+		let len = links.len();
+		links.retain(|rid| rid != rid_to);
+		let removed = links.len() != len;
+		if links.is_empty() {
+			self.links.remove(rid_from);
+		}
+		removed
 	}
 }
 
@@ -82,66 +121,115 @@ pub struct Scene {
 	nodes: Vec<ResourceId<Node>>,
 }
 
+// Links between nodes and resources of any type are created on demand
+// so that user types registered with ResourcePools::register() can be
+// assigned to nodes as well.
 impl Scene {
 	pub fn new() -> Self {
-		let mut links = HashMap::new();
-		Self::add_links::<Node, Mesh>(&mut links);
-		Self::add_links::<Mesh, Node>(&mut links);
-		Self::add_links::<Node, PerspectiveCamera>(&mut links);
-		Self::add_links::<PerspectiveCamera, Node>(&mut links);
-
 		Scene {
 			background_color: *Color::set(&mut Color::create(), 1.0, 1.0, 1.0),
-			links: links,
+			links: HashMap::new(),
 			nodes: Vec::new(),
 		}
 	}
 
-	fn add_links<T1: 'static, T2: 'static>(links: &mut HashMap<TypeId, Box<dyn ResourceLinksTrait>>) {
-		links.insert(TypeId::of::<(T1, T2)>(), Box::new(ResourceLinks::<T1, T2>::new()));
-	}
-
-	fn borrow_links<T1: 'static, T2: 'static>(&self) -> &ResourceLinks<T1, T2> {
-		if let Some(links) = self.links.get(&TypeId::of::<(T1, T2)>()) {
-			cast_links(links.as_ref())
-		} else {
-			// @TODO: Proper error handling
-			panic!("Unknown Type");
-		}
+	fn borrow_links<T1: 'static, T2: 'static>(&self) -> Option<&ResourceLinks<T1, T2>> {
+		self.links
+			.get(&TypeId::of::<(T1, T2)>())
+			.map(|links| cast_links(links.as_ref()))
 	}
 
 	fn borrow_links_mut<T1: 'static, T2: 'static>(&mut self) -> &mut ResourceLinks<T1, T2> {
-		if let Some(links) = self.links.get_mut(&TypeId::of::<(T1, T2)>()) {
-			cast_links_mut(links.as_mut())
-		} else {
-			// @TODO: Proper error handling
-			panic!("Unknown Type");
-		}
+		let links = self.links
+			.entry(TypeId::of::<(T1, T2)>())
+			.or_insert_with(|| Box::new(ResourceLinks::<T1, T2>::new()));
+		cast_links_mut(links.as_mut())
 	}
 
 	// Where should be this method placed?
+	// Assigning replaces the resources of the same type assigned to
+	// the node. Use attach() to assign more than one, for example
+	// meshes of the primitives of a glTF mesh.
 	pub fn assign<T: 'static>(
 		&mut self,
 		rid1: &ResourceId<Node>,
 		rid2: &ResourceId<T>,
+	) {
+		self.unassign::<T>(rid1);
+		self.attach(rid1, rid2);
+	}
+
+	// Adds a resource to the ones of the same type assigned to the node
+	pub fn attach<T: 'static>(
+		&mut self,
+		rid1: &ResourceId<Node>,
+		rid2: &ResourceId<T>,
 	) {
 		self.borrow_links_mut::<Node, T>().add(rid1, rid2);
-		self.borrow_links_mut::<T, Node>().add(rid2, rid1);
+		self.borrow_links_mut::<T, Node>().set(rid2, rid1);
+	}
+
+	// Returns false if the resource is not assigned to the node
+	pub fn detach<T: 'static>(
+		&mut self,
+		rid1: &ResourceId<Node>,
+		rid2: &ResourceId<T>,
+	) -> bool {
+		if !self.borrow_links_mut::<Node, T>().remove_link(rid1, rid2) {
+			return false;
+		}
+		let links = self.borrow_links_mut::<T, Node>();
+		// The resource may have been assigned to another node since
+		if links.borrow(rid2) == Some(rid1) {
+			links.remove(rid2);
+		}
+		true
+	}
+
+	// Returns the resources of type T that were assigned to the node
+	pub fn unassign<T: 'static>(
+		&mut self,
+		rid: &ResourceId<Node>,
+	) -> Vec<ResourceId<T>> {
+		let assigned = self.borrow_links_mut::<Node, T>().remove(rid);
+		let links = self.borrow_links_mut::<T, Node>();
+		for rid2 in assigned.iter() {
+			if links.borrow(rid2) == Some(rid) {
+				links.remove(rid2);
+			}
+		}
+		assigned
 	}
 
 	// @TODO: Rename?
 	pub fn borrow_assigned_from<T: 'static>(&self, rid: &ResourceId<T>) -> Option<&ResourceId<Node>> {
-		self.borrow_links::<T, Node>().borrow(rid)
+		self.borrow_links::<T, Node>()?.borrow(rid)
 	}
 
+	// The first one if more than one are assigned
 	pub fn borrow_assigned_to<T: 'static>(&self, rid: &ResourceId<Node>) -> Option<&ResourceId<T>> {
-		self.borrow_links::<Node, T>().borrow(rid)
+		self.borrow_links::<Node, T>()?.borrow(rid)
+	}
+
+	pub fn borrow_all_assigned_to<T: 'static>(&self, rid: &ResourceId<Node>) -> &[ResourceId<T>] {
+		match self.borrow_links::<Node, T>() {
+			Some(links) => links.borrow_all(rid),
+			None => &[],
+		}
 	}
 
+	// Adds a root node. Its descendants are in the scene as well, so
+	// a node attached to another one with NodeExecutor::add_child()
+	// should not be added or should be removed from the roots.
+	// SceneExecutor::add_child() does it.
 	pub fn add_node(&mut self, rid: &ResourceId<Node>) {
 		self.nodes.push(*rid);
 	}
 
+	pub fn remove_node(&mut self, rid: &ResourceId<Node>) {
+		self.nodes.retain(|node| node != rid);
+	}
+
 	pub fn collect_nodes(&self, pools: &ResourcePools) -> Vec<ResourceId<Node>> {
 		let mut nodes = Vec::new();
 		let pool = pools.borrow::<Node>();
@@ -151,6 +239,7 @@ impl Scene {
 		nodes
 	}
 
+	// sRGB encoded as material colors
 	pub fn borrow_background_color(&self) -> &[f32; 3] {
 		&self.background_color
 	}
@@ -159,12 +248,76 @@ impl Scene {
 		&mut self.background_color
 	}
 
-	pub fn update_matrices(&self, pools: &ResourcePools) {
-		// @TODO: Write comment about why unsafe
-		// @TODO: Remove unsafe
-		let pool = pools.borrow_mut_unsafe::<Node>();
+	pub fn update_matrices(&self, pool: &mut ResourcePool<Node>) {
 		for node in self.nodes.iter() {
 			NodeExecutor::update_matrices(pool, node);
 		}
 	}
 }
+
+pub struct SceneExecutor {
+}
+
+impl SceneExecutor {
+	// For a scene in the pools. Scene::update_matrices() can't be called
+	// with the scene and the node pool borrowed from the same pools.
+	pub fn update_matrices(
+		pools: &mut ResourcePools,
+		scene: &ResourceId<Scene>,
+	) -> Result<()> {
+		let nodes = pools.try_borrow::<Scene>()?
+			.borrow(scene)
+			.ok_or(Error::ResourceNotFound("Scene"))?
+			.nodes
+			.clone();
+		let pool = pools.try_borrow_mut::<Node>()?;
+		for node in nodes.iter() {
+			NodeExecutor::update_matrices(pool, node);
+		}
+		Ok(())
+	}
+
+	// NodeExecutor::add_child() for a node in the scene. The child is
+	// removed from the roots of the scene so that it isn't drawn twice.
+	pub fn add_child(
+		pools: &mut ResourcePools,
+		scene: &ResourceId<Scene>,
+		parent: &ResourceId<Node>,
+		child: &ResourceId<Node>,
+	) -> Result<()> {
+		Self::set_parent(pools, scene, child, Some(parent), false)
+	}
+
+	// NodeExecutor::set_parent() for a node in the scene. The node is
+	// removed from the roots of the scene when it gets a parent, and
+	// added to them when it becomes a root.
+	pub fn set_parent(
+		pools: &mut ResourcePools,
+		scene: &ResourceId<Scene>,
+		node: &ResourceId<Node>,
+		parent: Option<&ResourceId<Node>>,
+		keep_world_transform: bool,
+	) -> Result<()> {
+		if !pools.try_borrow::<Scene>()?.contains(scene) {
+			return Err(Error::ResourceNotFound("Scene"));
+		}
+
+		NodeExecutor::set_parent(
+			pools.try_borrow_mut::<Node>()?,
+			node,
+			parent,
+			keep_world_transform,
+		)?;
+
+		let scene = pools.try_borrow_mut::<Scene>()?
+			.borrow_mut(scene)
+			.ok_or(Error::ResourceNotFound("Scene"))?;
+		match parent {
+			Some(_) => scene.remove_node(node),
+			None => if !scene.nodes.contains(node) {
+				scene.add_node(node);
+			},
+		};
+		Ok(())
+	}
+}
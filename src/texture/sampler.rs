@@ -14,6 +14,7 @@ pub struct Sampler {
 	mag_filter: FilterMode,
 	min_filter: FilterMode,
 	mipmap_filter: FilterMode,
+	version: u64,
 	wrap_u: WrapMode,
 	wrap_v: WrapMode,
 	wrap_w: WrapMode,
@@ -48,6 +49,7 @@ impl Sampler {
 			mag_filter: desc.mag_filter,
 			min_filter: desc.min_filter,
 			mipmap_filter: desc.mipmap_filter,
+			version: 0,
 			wrap_u: desc.wrap_u,
 			wrap_v: desc.wrap_v,
 			wrap_w: desc.wrap_w,
@@ -77,4 +79,44 @@ impl Sampler {
 	pub fn wrap_w(&self) -> &WrapMode {
 		&self.wrap_w
 	}
+
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn set_mag_filter(&mut self, mode: FilterMode) -> &mut Self {
+		self.mag_filter = mode;
+		self.version += 1;
+		self
+	}
+
+	pub fn set_min_filter(&mut self, mode: FilterMode) -> &mut Self {
+		self.min_filter = mode;
+		self.version += 1;
+		self
+	}
+
+	pub fn set_mipmap_filter(&mut self, mode: FilterMode) -> &mut Self {
+		self.mipmap_filter = mode;
+		self.version += 1;
+		self
+	}
+
+	pub fn set_wrap_u(&mut self, mode: WrapMode) -> &mut Self {
+		self.wrap_u = mode;
+		self.version += 1;
+		self
+	}
+
+	pub fn set_wrap_v(&mut self, mode: WrapMode) -> &mut Self {
+		self.wrap_v = mode;
+		self.version += 1;
+		self
+	}
+
+	pub fn set_wrap_w(&mut self, mode: WrapMode) -> &mut Self {
+		self.wrap_w = mode;
+		self.version += 1;
+		self
+	}
 }
@@ -1,4 +1,5 @@
 // @TODO: Support more format
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub enum TextureFormat {
 	Float,
 	Uint8,
@@ -16,6 +17,7 @@ pub struct Texture {
 	format: TextureFormat,
 	height: u32,
 	texels: Vec<u8>, // @TODO: Support shared texels?
+	version: u64,
 	width: u32,
 }
 
@@ -30,6 +32,7 @@ impl Texture {
 			format: format,
 			height: height,
 			texels: texels,
+			version: 0,
 			width: width,
 		}
 	}
@@ -49,4 +52,31 @@ impl Texture {
 	pub fn borrow_texels(&self) -> &Vec<u8> {
 		&self.texels
 	}
+
+	// Incremented whenever the texels or the format can be changed
+	// so that the renderer knows when to re-upload them
+	pub fn get_version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn borrow_texels_mut(&mut self) -> &mut Vec<u8> {
+		self.version += 1;
+		&mut self.texels
+	}
+
+	// Replaces the whole image, for example to resize the texture
+	pub fn set_texels(
+		&mut self,
+		width: u32,
+		height: u32,
+		format: TextureFormat,
+		texels: Vec<u8>,
+	) -> &mut Self {
+		self.format = format;
+		self.height = height;
+		self.texels = texels;
+		self.width = width;
+		self.version += 1;
+		self
+	}
 }
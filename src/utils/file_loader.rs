@@ -1,3 +1,5 @@
+use crate::error::error::Result;
+
 pub struct FileLoader {
 }
 
@@ -8,8 +10,8 @@ use std::fs::File;
 
 #[cfg(not(target_arch = "wasm32"))]
 impl FileLoader {
-	pub async fn open(file_path: &str) -> File {
-		File::open(file_path).unwrap()
+	pub async fn open(file_path: &str) -> Result<File> {
+		Ok(File::open(file_path)?)
 	}
 }
 
@@ -17,6 +19,7 @@ impl FileLoader {
 
 #[cfg(target_arch = "wasm32")]
 use {
+	crate::error::error::Error,
 	std::io::Cursor,
 	wasm_bindgen::JsCast,
 	wasm_bindgen_futures::JsFuture,
@@ -28,18 +31,19 @@ use {
 	},
 };
 
-// @TODO: Proper error handling
 #[cfg(target_arch = "wasm32")]
 impl FileLoader {
-	pub async fn open(file_path: &str) -> Cursor<Vec<u8>> {
-		let result = fetch_as_binary(file_path).await.unwrap();
-		Cursor::new(result)
+	pub async fn open(file_path: &str) -> Result<Cursor<Vec<u8>>> {
+		match fetch_as_binary(file_path).await {
+			Ok(result) => Ok(Cursor::new(result)),
+			Err(message) => Err(Error::Fetch(format!("{}, {}", message, file_path))),
+		}
 	}
 }
 
 // @TODO: Proper error handling
 #[cfg(target_arch = "wasm32")]
-pub async fn fetch_as_binary(url: &str) -> Result<Vec<u8>, String> {
+pub async fn fetch_as_binary(url: &str) -> std::result::Result<Vec<u8>, String> {
 	let mut opts = RequestInit::new();
 	opts.method("GET");
 	opts.mode(RequestMode::Cors); // @TODO: Should be able to opt-out
@@ -49,7 +53,10 @@ pub async fn fetch_as_binary(url: &str) -> Result<Vec<u8>, String> {
 		Err(_e) => return Err("Failed to create request".to_string()),
 	};
 
-	let window = web_sys::window().unwrap();
+	let window = match web_sys::window() {
+		Some(window) => window,
+		None => return Err("Failed to get window".to_string()),
+	};
 	let response = match JsFuture::from(window.fetch_with_request(&request)).await {
 		Ok(response) => response,
 		Err(_e) => return Err("Failed to fetch".to_string()),
@@ -0,0 +1,78 @@
+// Caps the frame rate of a render loop. Not available on Wasm
+// because browsers pace frames with requestAnimationFrame().
+//
+// With winit, set ControlFlow::WaitUntil(limiter.next_frame_time())
+// and call limiter.tick() when a frame is rendered, or call
+// limiter.wait() right before rendering in a busy loop.
+
+use std::time::{
+	Duration,
+	Instant,
+};
+
+pub struct FrameLimiter {
+	frame_duration: Option<Duration>,
+	next_frame_time: Instant,
+}
+
+impl FrameLimiter {
+	// max_fps 0.0 or less means no limit
+	pub fn new(max_fps: f64) -> Self {
+		FrameLimiter {
+			frame_duration: to_frame_duration(max_fps),
+			next_frame_time: Instant::now(),
+		}
+	}
+
+	pub fn set_max_fps(&mut self, max_fps: f64) -> &mut Self {
+		self.frame_duration = to_frame_duration(max_fps);
+		self
+	}
+
+	pub fn get_max_fps(&self) -> f64 {
+		match self.frame_duration {
+			Some(duration) => 1.0 / duration.as_secs_f64(),
+			None => 0.0,
+		}
+	}
+
+	// Time the next frame should start at
+	pub fn next_frame_time(&self) -> Instant {
+		self.next_frame_time
+	}
+
+	pub fn is_frame_due(&self) -> bool {
+		Instant::now() >= self.next_frame_time
+	}
+
+	// Marks a frame as started and schedules the next one. If the loop
+	// falls behind, the schedule restarts from now rather than rendering
+	// the missed frames back to back.
+	pub fn tick(&mut self) -> &mut Self {
+		let now = Instant::now();
+		self.next_frame_time = match self.frame_duration {
+			Some(duration) => {
+				let next = self.next_frame_time + duration;
+				if next < now { now + duration } else { next }
+			},
+			None => now,
+		};
+		self
+	}
+
+	// Blocks the current thread until the next frame is due, then ticks.
+	pub fn wait(&mut self) -> &mut Self {
+		let now = Instant::now();
+		if self.next_frame_time > now {
+			std::thread::sleep(self.next_frame_time - now);
+		}
+		self.tick()
+	}
+}
+
+fn to_frame_duration(max_fps: f64) -> Option<Duration> {
+	match max_fps > 0.0 {
+		true => Some(Duration::from_secs_f64(1.0 / max_fps)),
+		false => None,
+	}
+}
@@ -4,6 +4,10 @@ use crate::{
 		attribute::Attribute,
 		geometry::Geometry,
 		index::Index,
+		interleaved_buffer::{
+			InterleavedAttribute,
+			InterleavedBuffer,
+		},
 	},
 	resource::resource::{
 		ResourceId,
@@ -40,14 +44,14 @@ impl GeometryHelper {
 		].to_vec();
 
 		let indices = [
-			0, 1, 2,
+			0_u32, 1, 2,
 		].to_vec();
 
 		let mut geometry = Geometry::new();
 		geometry.set_attribute("position", pools.borrow_mut::<Attribute>().add(Attribute::new(positions, 3)));
 		geometry.set_attribute("normal", pools.borrow_mut::<Attribute>().add(Attribute::new(normals, 3)));
 		geometry.set_attribute("uv", pools.borrow_mut::<Attribute>().add(Attribute::new(uvs, 2)));
-		geometry.set_index(pools.borrow_mut::<Index>().add(Index::new(indices)));
+		geometry.set_index(pools.borrow_mut::<Index>().add(Index::from_u32(indices)));
 		pools.borrow_mut::<Geometry>().add(geometry)
 	}
 
@@ -90,7 +94,7 @@ impl GeometryHelper {
 		].to_vec();
 
 		let indices = [
-			0, 2, 1,
+			0_u32, 2, 1,
 			1, 2, 3,
 		].to_vec();
 
@@ -98,7 +102,7 @@ impl GeometryHelper {
 		geometry.set_attribute("position", pools.borrow_mut::<Attribute>().add(Attribute::new(positions, 3)));
 		geometry.set_attribute("normal", pools.borrow_mut::<Attribute>().add(Attribute::new(normals, 3)));
 		geometry.set_attribute("uv", pools.borrow_mut::<Attribute>().add(Attribute::new(uvs, 2)));
-		geometry.set_index(pools.borrow_mut::<Index>().add(Index::new(indices)));
+		geometry.set_index(pools.borrow_mut::<Index>().add(Index::from_u32(indices)));
 		pools.borrow_mut::<Geometry>().add(geometry)
 	}
 
@@ -116,7 +120,7 @@ impl GeometryHelper {
 		let mut position_vec = Vector3::create();
 
 		// @TODO: Clean up
-		for face in 0..6 {
+		for face in 0..6_u32 {
 			let (x, y, z, nx, ny, nz, dx, dy, dz) = match face {
 				// front
 				0 => (
@@ -207,7 +211,91 @@ impl GeometryHelper {
 		geometry.set_attribute("position", pools.borrow_mut::<Attribute>().add(Attribute::new(positions, 3)));
 		geometry.set_attribute("normal", pools.borrow_mut::<Attribute>().add(Attribute::new(normals, 3)));
 		geometry.set_attribute("uv", pools.borrow_mut::<Attribute>().add(Attribute::new(uvs, 2)));
-		geometry.set_index(pools.borrow_mut::<Index>().add(Index::new(indices)));
+		geometry.set_index(pools.borrow_mut::<Index>().add(Index::from_u32(indices)));
 		pools.borrow_mut::<Geometry>().add(geometry)
 	}
+
+	// Moves the attributes of the geometry into a new InterleavedBuffer so that
+	// they are bound as one vertex buffer. Items are padded as in separate
+	// vertex buffers. The attributes are left in the pool because other
+	// geometries may share them. Attributes with item size over 4 or whose
+	// count differs from position's are kept separate.
+	pub fn interleave(
+		pools: &mut ResourcePools,
+		geometry_rid: &ResourceId<Geometry>,
+	) -> Option<ResourceId<InterleavedBuffer>> {
+		let geometry = pools.borrow::<Geometry>().borrow(geometry_rid)?;
+		let attribute_pool = pools.borrow::<Attribute>();
+
+		let mut names = geometry.borrow_attributes().keys().collect::<Vec<_>>();
+		names.sort();
+
+		let count = geometry.borrow_attribute("position")
+			.or_else(|| names.first().and_then(|name| geometry.borrow_attribute(name)))
+			.and_then(|rid| attribute_pool.borrow(rid))?
+			.get_count();
+
+		let attributes = names
+			.into_iter()
+			.filter_map(|name| {
+				let attribute = attribute_pool.borrow(geometry.borrow_attribute(name)?)?;
+				match attribute.get_count() == count &&
+					attribute.get_item_size() >= 1 && attribute.get_item_size() <= 4 {
+					true => Some((name.clone(), attribute)),
+					false => None,
+				}
+			})
+			.collect::<Vec<_>>();
+
+		// Aligned items are multiples of 4 bytes so the offsets
+		// and the stride are aligned
+		let mut offsets = Vec::new();
+		let mut stride = 0;
+		for (_, attribute) in attributes.iter() {
+			offsets.push(stride);
+			stride += attribute.get_aligned_size();
+		}
+
+		if stride == 0 {
+			return None;
+		}
+
+		let mut data = Vec::with_capacity((count * stride) as usize);
+		for i in 0..count as usize {
+			for (_, attribute) in attributes.iter() {
+				let component_size = attribute.get_type().get_size() as usize;
+				let item_bytes = attribute.get_item_size() as usize * component_size;
+				let aligned_size = attribute.get_aligned_size() as usize;
+				let bytes = attribute.borrow_data().borrow_bytes();
+				data.extend_from_slice(&bytes[i * item_bytes..(i + 1) * item_bytes]);
+				data.resize(data.len() + aligned_size - item_bytes, 0);
+			}
+		}
+
+		let layouts = attributes
+			.iter()
+			.zip(offsets.iter())
+			.map(|((name, attribute), offset)| (
+				name.clone(),
+				attribute.get_type(),
+				attribute.get_item_size(),
+				*offset,
+				attribute.is_normalized(),
+			))
+			.collect::<Vec<_>>();
+
+		let buffer_rid = pools.borrow_mut::<InterleavedBuffer>().add(InterleavedBuffer::new(data, stride));
+		let geometry = pools.borrow_mut::<Geometry>().borrow_mut(geometry_rid).unwrap();
+		for (name, attribute_type, item_size, offset, normalized) in layouts.into_iter() {
+			geometry.set_interleaved_attribute(&name, InterleavedAttribute::new(
+				buffer_rid,
+				attribute_type,
+				item_size,
+				offset,
+				normalized,
+			));
+		}
+
+		Some(buffer_rid)
+	}
 }
@@ -1,8 +1,20 @@
-use gltf::Gltf;
+use std::collections::HashMap;
+
+use gltf::{
+	accessor::DataType,
+	Gltf,
+};
 
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	geometry::{
-		attribute::Attribute,
+		attribute::{
+			Attribute,
+			AttributeData,
+		},
 		geometry::Geometry,
 		index::Index,
 	},
@@ -32,7 +44,6 @@ use crate::{
 		},
 	},
 	math::{
-		euler::Euler,
 		matrix4::Matrix4,
 		vector3::Vector3,
 	},
@@ -42,7 +53,10 @@ use crate::{
 	},
 	scene::{
 		mesh::Mesh,
-		node::Node,
+		node::{
+			Node,
+			NodeExecutor,
+		},
 		scene::Scene,
 	},
 	texture::{
@@ -63,88 +77,159 @@ use crate::{
 	},
 };
 
-async fn parse_attribute(
+fn decode_index(bytes: &[u8], size: usize) -> u32 {
+	match size {
+		1 => bytes[0] as u32,
+		2 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+		_ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+	}
+}
+
+// Reads the components of the accessor elements into a flat Vec,
+// taking the byte offset, the byte stride of interleaved views and
+// sparse substitution into account. Elements are zeros without
+// a buffer view.
+fn read_accessor<T: Copy + Default>(
+	asset: &GltfAsset,
+	accessor: &gltf::Accessor<'_>,
+	decode: impl Fn(&[u8]) -> T,
+) -> Result<Vec<T>> {
+	let out_of_bounds = || Error::InvalidAsset(format!(
+		"Accessor {} is out of bounds in {}",
+		accessor.index(),
+		asset.filename,
+	));
+
+	let components = accessor.dimensions().multiplicity();
+	let component_size = accessor.data_type().size();
+	let element_size = components * component_size;
+	let count = accessor.count();
+
+	let mut values = vec![T::default(); count * components];
+
+	if let Some(view) = accessor.view() {
+		let data = asset.borrow_view_data(&view)?;
+		let stride = view.stride().unwrap_or(element_size);
+		for i in 0..count {
+			let offset = accessor.offset() + i * stride;
+			let element = data.get(offset..offset + element_size).ok_or_else(out_of_bounds)?;
+			for (j, bytes) in element.chunks_exact(component_size).enumerate() {
+				values[i * components + j] = decode(bytes);
+			}
+		}
+	}
+
+	if let Some(sparse) = accessor.sparse() {
+		let indices_def = sparse.indices();
+		let index_size = indices_def.index_type().size();
+		let indices = asset.borrow_view_data(&indices_def.view())?
+			.get(indices_def.offset() as usize..)
+			.ok_or_else(out_of_bounds)?;
+		let values_def = sparse.values();
+		let sparse_values = asset.borrow_view_data(&values_def.view())?
+			.get(values_def.offset() as usize..)
+			.ok_or_else(out_of_bounds)?;
+
+		for i in 0..sparse.count() as usize {
+			let index = indices.get(i * index_size..(i + 1) * index_size)
+				.map(|bytes| decode_index(bytes, index_size) as usize)
+				.filter(|index| *index < count)
+				.ok_or_else(out_of_bounds)?;
+			let element = sparse_values.get(i * element_size..(i + 1) * element_size)
+				.ok_or_else(out_of_bounds)?;
+			for (j, bytes) in element.chunks_exact(component_size).enumerate() {
+				values[index * components + j] = decode(bytes);
+			}
+		}
+	}
+
+	Ok(values)
+}
+
+fn parse_attribute(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
 	primitive: &gltf::Attribute<'_>,
-) -> (&'static str, ResourceId<Attribute>) {
+) -> Result<(String, ResourceId<Attribute>)> {
 	let (semantic, accessor) = primitive;
 	use gltf::mesh::Semantic;
-	if let Some(view) = accessor.view() {
-		let offset = view.offset();
-		let length = view.length();
-		let buffer = view.buffer();
-
-		use gltf::buffer::Source;
-		use std::io::{Read, Seek, SeekFrom};
-		let data = match buffer.source() {
-			Source::Bin => {
-				panic!("Bin is not supported yet");
-			},
-			Source::Uri(uri) => {
-				let mut buf = [0_u8; 4];
-				let mut data = Vec::<f32>::new();
-				let mut file = FileLoader::open(
-					&(path.to_owned() + uri),
-				).await;
-				for i in 0..(length / 4) {
-					file.seek(SeekFrom::Start((offset + i * 4) as u64)).unwrap();
-					file.read_exact(&mut buf).unwrap();
-					data.push(f32::from_le_bytes(buf));
-				}
-				data
-			}
-		};
 
-		let (name, attribute) = match semantic {
-			Semantic::Normals => {(
-				"normal",
-				Attribute::new(data, 3),
-			)},
-			Semantic::Positions => {(
-				"position",
-				Attribute::new(data, 3),
-			)},
-			Semantic::TexCoords(_) => {(
-				"uv",
-				Attribute::new(data, 2),
-			)},
-			_ => {
-				panic!("Unsupport accessor semantic.");
-			},
-		};
+	let name = match semantic {
+		Semantic::Colors(0) => "color".to_string(),
+		Semantic::Colors(set) => format!("color{}", set),
+		Semantic::Joints(0) => "joints".to_string(),
+		Semantic::Joints(set) => format!("joints{}", set),
+		Semantic::Normals => "normal".to_string(),
+		Semantic::Positions => "position".to_string(),
+		Semantic::Tangents => "tangent".to_string(),
+		Semantic::TexCoords(0) => "uv".to_string(),
+		Semantic::TexCoords(set) => format!("uv{}", set),
+		Semantic::Weights(0) => "weights".to_string(),
+		Semantic::Weights(set) => format!("weights{}", set),
+	};
 
-		(name, pools.borrow_mut::<Attribute>().add(attribute))
-	} else {
-		panic!("Sparse accessor is not supported yet.");
-	}
+	// Stored as is so that quantized data can be uploaded without conversion
+	let data = match accessor.data_type() {
+		DataType::F32 => AttributeData::Float32(read_accessor(asset, accessor, |bytes| {
+			f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+		})?),
+		DataType::I8 => AttributeData::Sint8(read_accessor(asset, accessor, |bytes| {
+			bytes[0] as i8
+		})?),
+		DataType::I16 => AttributeData::Sint16(read_accessor(asset, accessor, |bytes| {
+			i16::from_le_bytes([bytes[0], bytes[1]])
+		})?),
+		DataType::U8 => AttributeData::Uint8(read_accessor(asset, accessor, |bytes| {
+			bytes[0]
+		})?),
+		DataType::U16 => AttributeData::Uint16(read_accessor(asset, accessor, |bytes| {
+			u16::from_le_bytes([bytes[0], bytes[1]])
+		})?),
+		DataType::U32 => AttributeData::Uint32(read_accessor(asset, accessor, |bytes| {
+			u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+		})?),
+	};
+
+	let attribute = Attribute::new_typed(
+		data,
+		accessor.dimensions().multiplicity() as u32,
+		accessor.normalized(),
+	);
+
+	Ok((name, pools.borrow_mut::<Attribute>().add(attribute)))
 }
 
-async fn parse_geometry(
+fn parse_geometry(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
 	primitive_def: &gltf::Primitive<'_>,
-) -> ResourceId<Geometry> {
+) -> Result<ResourceId<Geometry>> {
 	let mut geometry = Geometry::new();
 
 	for attribute_def in primitive_def.attributes() {
-		let (name, attribute) = parse_attribute(pools, path, &attribute_def).await;
+		let (name, attribute) = parse_attribute(pools, asset, &attribute_def)?;
 		geometry.set_attribute(&name, attribute);
 	}
 
 	if let Some(accessor) = primitive_def.indices() {
-		let index = parse_index(pools, path, &accessor).await;
+		let index = parse_index(pools, asset, &accessor)?;
 		geometry.set_index(index);
 	}
 
-	pools.borrow_mut::<Geometry>().add(geometry)
+	Ok(pools.borrow_mut::<Geometry>().add(geometry))
 }
 
-async fn parse_material(
+fn parse_material(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
+	cache: &mut Cache,
 	material_def: &gltf::Material<'_>,
-) -> ResourceId<Material> {
+) -> Result<ResourceId<Material>> {
+	// None is the default material
+	if let Some(material) = cache.materials.get(&material_def.index()) {
+		return Ok(*material);
+	}
+
 	let pbr_metallic_roughness = material_def.pbr_metallic_roughness();
 
 	// Base color
@@ -158,7 +243,7 @@ async fn parse_material(
 	));
 
 	let base_color = if let Some(info) = pbr_metallic_roughness.base_color_texture() {
-		let (texture, sampler) = parse_texture_info(pools, path, &info, TextureFormat::Uint8Srgb).await;
+		let (texture, sampler) = parse_texture_info(pools, asset, cache, &info, TextureFormat::Uint8Srgb)?;
 
 		let texture_node = pools.borrow_mut::<Box<dyn MaterialNode>>().add(
 			Box::new(TextureNode::new(texture, sampler)),
@@ -189,7 +274,7 @@ async fn parse_material(
 	);
 
 	let (metallic, roughness) = if let Some(info) = pbr_metallic_roughness.metallic_roughness_texture() {
-		let (texture, sampler) = parse_texture_info(pools, path, &info, TextureFormat::default()).await;
+		let (texture, sampler) = parse_texture_info(pools, asset, cache, &info, TextureFormat::default())?;
 
 		let texture_node = pools.borrow_mut::<Box<dyn MaterialNode>>().add(
 			Box::new(TextureNode::new(texture, sampler)),
@@ -219,7 +304,7 @@ async fn parse_material(
 	// Normal
 
 	let normal = if let Some(info) = material_def.normal_texture() {
-		let (texture, sampler) = parse_normal_texture_info(pools, path, &info).await;
+		let (texture, sampler) = parse_normal_texture_info(pools, asset, cache, &info)?;
 
 		let texture_node = pools.borrow_mut::<Box<dyn MaterialNode>>().add(
 			Box::new(TextureNode::new(texture, sampler)),
@@ -275,7 +360,7 @@ async fn parse_material(
 	));
 
 	let emissive = if let Some(info) = material_def.emissive_texture() {
-		let (texture, sampler) = parse_texture_info(pools, path, &info, TextureFormat::Uint8Srgb).await;
+		let (texture, sampler) = parse_texture_info(pools, asset, cache, &info, TextureFormat::Uint8Srgb)?;
 
 		let texture_node = pools.borrow_mut::<Box<dyn MaterialNode>>().add(
 			Box::new(TextureNode::new(texture, sampler)),
@@ -299,24 +384,31 @@ async fn parse_material(
 		),
 	));
 
-	pools.borrow_mut::<Material>().add(Material::new(add, Side::default()))
+	let material = pools.borrow_mut::<Material>().add(Material::new(add, Side::default()));
+	cache.materials.insert(material_def.index(), material);
+	Ok(material)
 }
 
-async fn parse_node(
+fn parse_node(
 	pools: &mut ResourcePools,
 	scene: &ResourceId<Scene>,
-	path: &str,
+	asset: &GltfAsset,
+	cache: &mut Cache,
 	node_def: &gltf::Node<'_>,
-) -> ResourceId<Node> {
+) -> Result<ResourceId<Node>> {
 	let mut node = Node::new();
 
 	match node_def.transform() {
 		gltf::scene::Transform::Matrix {
 			matrix,
 		} => {
-			let mut matrix2 = Matrix4::create();
-			let matrix = Matrix4::set_from_2d_array(&mut matrix2, &matrix);
-			node.set_matrix(&matrix);
+			// glTF matrices are arrays of columns that are
+			// already in our column-major element order
+			let mut elements = Matrix4::create();
+			for (i, column) in matrix.iter().enumerate() {
+				elements[i * 4..i * 4 + 4].copy_from_slice(column);
+			}
+			node.set_matrix(&elements);
 		},
 		gltf::scene::Transform::Decomposed {
 			translation,
@@ -324,79 +416,63 @@ async fn parse_node(
 			scale,
 		} => {
 			Vector3::copy(node.borrow_position_mut(), &translation);
-			Euler::set_from_quaternion(node.borrow_rotation_mut(), &rotation);
+			node.set_quaternion(&rotation);
 			Vector3::copy(node.borrow_scale_mut(), &scale);
 			node.update_matrix();
 		},
 	};
 
-	let node = pools.borrow_mut::<Node>().add(Node::new());
+	let node = pools.borrow_mut::<Node>().add(node);
 
 	if let Some(mesh_def) = node_def.mesh() {
 		for primitive_def in mesh_def.primitives() {
-			let (geometry, material) = parse_primitive(pools, path, &primitive_def).await;
+			let (geometry, material) = parse_primitive(pools, asset, cache, &primitive_def)?;
 			let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
-			pools.borrow_mut::<Scene>().borrow_mut(scene).unwrap().assign(&node, &mesh);
+			match pools.borrow_mut::<Scene>().borrow_mut(scene) {
+				Some(scene) => scene.attach(&node, &mesh),
+				None => return Err(Error::ResourceNotFound("Scene")),
+			};
 		}
 	}
 
-	node
+	for child_def in node_def.children() {
+		let child = parse_node(pools, scene, asset, cache, &child_def)?;
+		NodeExecutor::add_child(pools.borrow_mut::<Node>(), &node, &child)?;
+	}
+
+	Ok(node)
 }
 
-async fn parse_normal_texture_info(
+fn parse_normal_texture_info(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
+	cache: &mut Cache,
 	info: &gltf::material::NormalTexture<'_>,
-) -> (ResourceId<Texture>, ResourceId<Sampler>) {
-	parse_texture(pools, path, &info.texture(), TextureFormat::default()).await
+) -> Result<(ResourceId<Texture>, ResourceId<Sampler>)> {
+	parse_texture(pools, asset, cache, &info.texture(), TextureFormat::default())
 }
 
-async fn parse_index(
+fn parse_index(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
 	index: &gltf::Accessor<'_>,
-) -> ResourceId<Index> {
-	if let Some(view) = index.view() {
-		let offset = view.offset();
-		let length = view.length();
-		let buffer = view.buffer();
-
-		use gltf::buffer::Source;
-		use std::io::{Read, Seek, SeekFrom};
-		let data = match buffer.source() {
-			Source::Bin => {
-				panic!("Bin is not supported yet");
-			},
-			Source::Uri(uri) => {
-				let mut buf = [0_u8; 2];
-				let mut data = Vec::<u16>::new();
-				let mut file = FileLoader::open(
-					&(path.to_owned() + uri),
-				).await;
-				for i in 0..(length / 2) {
-					file.seek(SeekFrom::Start((offset + i * 2) as u64)).unwrap();
-					file.read_exact(&mut buf).unwrap();
-					data.push(u16::from_le_bytes(buf));
-				}
-				data
-			}
-		};
+) -> Result<ResourceId<Index>> {
+	let size = index.data_type().size();
+	let data = read_accessor(asset, index, |bytes| decode_index(bytes, size))?;
 
-		pools.borrow_mut::<Index>().add(Index::new(data))
-	} else {
-		panic!("Sparse accessor is not supported yet.");
-	}
+	Ok(pools.borrow_mut::<Index>().add(Index::from_u32(data)))
 }
 
-async fn parse_primitive(
+fn parse_primitive(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
+	cache: &mut Cache,
 	primitive_def: &gltf::Primitive<'_>,
-) -> (ResourceId<Geometry>, ResourceId<Material>) {
-	(
-		parse_geometry(pools, path, primitive_def).await,
-		parse_material(pools, path, &primitive_def.material()).await
-	)
+) -> Result<(ResourceId<Geometry>, ResourceId<Material>)> {
+	Ok((
+		parse_geometry(pools, asset, primitive_def)?,
+		parse_material(pools, asset, cache, &primitive_def.material())?,
+	))
 }
 
 fn parse_sampler(
@@ -450,61 +526,229 @@ fn parse_sampler(
 	))
 }
 
-async fn parse_texture(
+fn parse_texture(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
+	cache: &mut Cache,
 	texture_def: &gltf::Texture<'_>,
 	format: TextureFormat
-) -> (ResourceId<Texture>, ResourceId<Sampler>) {
-	let source = texture_def.source();
-
-	use gltf::image::Source;
-	let texture = match source.source() {
-		Source::Uri {uri, mime_type: _mime_type} => {
-			TextureLoader::load_with_filepath(
-				pools,
-				&(path.to_owned() + uri),
+) -> Result<(ResourceId<Texture>, ResourceId<Sampler>)> {
+	let image_index = texture_def.source().index();
+
+	// The same image can be used as sRGB and linear textures
+	let texture = match cache.textures.get(&(image_index, format)) {
+		Some(texture) => *texture,
+		None => {
+			let image = &asset.images[image_index];
+			let texture = pools.borrow_mut::<Texture>().add(Texture::new(
+				image.get_width(),
+				image.get_height(),
 				format,
-			).await
+				image.borrow_texels().clone(),
+			));
+			cache.textures.insert((image_index, format), texture);
+			texture
 		},
-		Source::View {..} => {
-			panic!("Unsuppored");
+	};
+
+	// None is the default sampler
+	let sampler_def = texture_def.sampler();
+	let sampler = match cache.samplers.get(&sampler_def.index()) {
+		Some(sampler) => *sampler,
+		None => {
+			let sampler = parse_sampler(pools, &sampler_def);
+			cache.samplers.insert(sampler_def.index(), sampler);
+			sampler
 		},
 	};
 
-	(texture, parse_sampler(pools, &texture_def.sampler()))
+	Ok((texture, sampler))
 }
 
-async fn parse_texture_info(
+fn parse_texture_info(
 	pools: &mut ResourcePools,
-	path: &str,
+	asset: &GltfAsset,
+	cache: &mut Cache,
 	info: &gltf::texture::Info<'_>,
 	format: TextureFormat,
-) -> (ResourceId<Texture>, ResourceId<Sampler>) {
-	parse_texture(pools, path, &info.texture(), format).await
+) -> Result<(ResourceId<Texture>, ResourceId<Sampler>)> {
+	parse_texture(pools, asset, cache, &info.texture(), format)
+}
+
+// Splits a data URI into the media type and the decoded data.
+// Returns None if the URI is not a data URI.
+fn decode_data_uri(uri: &str) -> Option<Result<(&str, Vec<u8>)>> {
+	let rest = uri.strip_prefix("data:")?;
+	Some(match rest.find(";base64,") {
+		Some(position) => base64::decode(&rest[position + ";base64,".len()..])
+			.map(|data| (&rest[..position], data))
+			.map_err(Error::from),
+		None => Err(Error::Unsupported("Data URI not encoded in Base64".to_string())),
+	})
+}
+
+fn slice_view<'a>(
+	buffers: &'a [Vec<u8>],
+	view: &gltf::buffer::View<'_>,
+	filename: &str,
+) -> Result<&'a [u8]> {
+	buffers[view.buffer().index()]
+		.get(view.offset()..view.offset() + view.length())
+		.ok_or(Error::InvalidAsset(format!(
+			"Buffer view {} is out of bounds in {}",
+			view.index(),
+			filename,
+		)))
+}
+
+// Resources built from a glTF asset by glTF index so that
+// the ones referred to more than once are built once
+struct Cache {
+	materials: HashMap<Option<usize>, ResourceId<Material>>,
+	samplers: HashMap<Option<usize>, ResourceId<Sampler>>,
+	// By image index and format
+	textures: HashMap<(usize, TextureFormat), ResourceId<Texture>>,
+}
+
+impl Cache {
+	fn new() -> Self {
+		Cache {
+			materials: HashMap::new(),
+			samplers: HashMap::new(),
+			textures: HashMap::new(),
+		}
+	}
+}
+
+// Files of a glTF asset read in advance. Building resources from it
+// doesn't do any IO so that reading can be done on a worker thread
+// and building on the thread owning ResourcePools.
+pub struct GltfAsset {
+	buffers: Vec<Vec<u8>>,
+	document: gltf::Document,
+	filename: String,
+	images: Vec<Texture>,
+}
+
+impl GltfAsset {
+	fn borrow_view_data(&self, view: &gltf::buffer::View<'_>) -> Result<&[u8]> {
+		slice_view(&self.buffers, view, &self.filename)
+	}
 }
 
 pub struct GltfLoader{
 }
 
 impl GltfLoader {
-	pub async fn load_gltf(
-		pools: &mut ResourcePools,
-		scene: &ResourceId<Scene>,
+	// Reads the glTF or GLB file and the buffers and images it refers
+	// to. Buffers and images can be external files, data URIs, the
+	// GLB binary chunk or buffer views.
+	pub async fn read_gltf(
 		path: &str,
 		filename: &str,
-	) -> Vec<ResourceId<Node>> {
-		let gltf = Gltf::from_reader(
-			FileLoader::open(&(path.to_owned() + filename)).await,
-		).unwrap();
+	) -> Result<GltfAsset> {
+		let Gltf {document, mut blob} = Gltf::from_reader(
+			FileLoader::open(&(path.to_owned() + filename)).await?,
+		)?;
+
+		let mut buffers = Vec::new();
+		for buffer_def in document.buffers() {
+			use gltf::buffer::Source;
+			let data = match buffer_def.source() {
+				Source::Bin => match blob.take() {
+					Some(blob) => blob,
+					None => return Err(Error::InvalidAsset(format!(
+						"Binary chunk is missing in {}",
+						filename,
+					))),
+				},
+				Source::Uri(uri) => match decode_data_uri(uri) {
+					Some(result) => result?.1,
+					None => {
+						use std::io::Read;
+						let mut data = Vec::new();
+						FileLoader::open(&(path.to_owned() + uri)).await?
+							.read_to_end(&mut data)?;
+						data
+					},
+				},
+			};
+			if data.len() < buffer_def.length() {
+				return Err(Error::InvalidAsset(format!(
+					"Buffer {} is shorter than its byteLength in {}",
+					buffer_def.index(),
+					filename,
+				)));
+			}
+			buffers.push(data);
+		}
 
+		let mut images = Vec::new();
+		for image_def in document.images() {
+			use gltf::image::Source;
+			images.push(match image_def.source() {
+				Source::Uri {uri, mime_type} => match decode_data_uri(uri) {
+					Some(result) => {
+						let (media_type, data) = result?;
+						TextureLoader::decode_with_mime_type(
+							data.as_slice(),
+							mime_type.unwrap_or(media_type),
+							TextureFormat::default(),
+						)?
+					},
+					None => TextureLoader::read_with_filepath(
+						&(path.to_owned() + uri),
+						TextureFormat::default(),
+					).await?,
+				},
+				Source::View {view, mime_type} => TextureLoader::decode_with_mime_type(
+					slice_view(&buffers, &view, filename)?,
+					mime_type,
+					TextureFormat::default(),
+				)?,
+			});
+		}
+
+		Ok(GltfAsset {
+			buffers: buffers,
+			document: document,
+			filename: filename.to_string(),
+			images: images,
+		})
+	}
+
+	// Adds the resources of the default scene, or the first scene if
+	// no default, to the pools. Returns the root nodes.
+	pub fn build(
+		pools: &mut ResourcePools,
+		scene: &ResourceId<Scene>,
+		asset: &GltfAsset,
+	) -> Result<Vec<ResourceId<Node>>> {
 		let mut nodes = Vec::new();
 
-		let scene_def = gltf.default_scene().unwrap();
+		let scene_def = match asset.document.default_scene() {
+			Some(scene_def) => scene_def,
+			None => match asset.document.scenes().next() {
+				Some(scene_def) => scene_def,
+				None => return Err(Error::InvalidAsset(format!("No scene in {}", asset.filename))),
+			},
+		};
+
+		let mut cache = Cache::new();
 		for node_def in scene_def.nodes() {
-			nodes.push(parse_node(pools, scene, path, &node_def).await);
+			nodes.push(parse_node(pools, scene, asset, &mut cache, &node_def)?);
 		}
 
-		nodes
+		Ok(nodes)
 	}
-}
\ No newline at end of file
+
+	pub async fn load_gltf(
+		pools: &mut ResourcePools,
+		scene: &ResourceId<Scene>,
+		path: &str,
+		filename: &str,
+	) -> Result<Vec<ResourceId<Node>>> {
+		let asset = Self::read_gltf(path, filename).await?;
+		Self::build(pools, scene, &asset)
+	}
+}
@@ -1,4 +1,6 @@
 pub mod file_loader;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod frame_limiter;
 pub mod log;
 pub mod geometry_helper;
 pub mod gltf_loader;
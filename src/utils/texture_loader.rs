@@ -1,6 +1,10 @@
 use crate::utils::file_loader::FileLoader;
 
 use crate::{
+	error::error::{
+		Error,
+		Result,
+	},
 	resource::resource::{
 		ResourceId,
 		ResourcePools,
@@ -13,101 +17,185 @@ use crate::{
 	},
 };
 
+// read_* and decode_* functions don't touch ResourcePools so that they can
+// run on a worker thread. Add the returned Texture to the pools on the
+// thread owning them. load_* functions do both.
 pub struct TextureLoader {
 }
 
 impl TextureLoader {
-	pub fn load_png<R: std::io::Read>(
-		pools: &mut ResourcePools,
+	pub fn decode_png<R: std::io::Read>(
 		reader: R,
 		// @TODO: Should use default rather than Option?
 		format: TextureFormat,
-	) -> ResourceId<Texture> {
-		let decoder = png::Decoder::new(reader);
-		let mut reader = decoder.read_info().unwrap();
-		let (width, height) = {
-			let info = reader.info();
-			(info.width, info.height)
-		};
+	) -> Result<Texture> {
+		let mut decoder = png::Decoder::new(reader);
+		// Palette and low bit depth to 8 bits, 16 bits to 8 bits
+		decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+		let mut reader = decoder.read_info()?;
 		let mut buf = vec![0; reader.output_buffer_size()];
-		reader.next_frame(&mut buf).unwrap();
-
-		pools.borrow_mut::<Texture>().add(
-			Texture::new(
-				width,
-				height,
-				format,
-				buf,
-			)
-		)
+		let info = reader.next_frame(&mut buf)?;
+		buf.truncate(info.buffer_size());
+
+		if info.bit_depth != png::BitDepth::Eight {
+			return Err(Error::Unsupported(format!("PNG bit depth {:?}", info.bit_depth)));
+		}
+
+		let data = match info.color_type {
+			png::ColorType::Grayscale => convert_gray_to_rgba(&buf),
+			png::ColorType::GrayscaleAlpha => convert_gray_alpha_to_rgba(&buf),
+			png::ColorType::Rgb => convert_rgb_to_rgba(&buf),
+			png::ColorType::Rgba => buf,
+			png::ColorType::Indexed => {
+				return Err(Error::Unsupported("PNG indexed color".to_string()));
+			},
+		};
+
+		Ok(Texture::new(
+			info.width,
+			info.height,
+			format,
+			data,
+		))
+	}
+
+	pub fn decode_jpg<R: std::io::Read>(
+		reader: R,
+		format: TextureFormat,
+	) -> Result<Texture> {
+		let mut decoder = jpeg_decoder::Decoder::new(reader);
+		let pixels = decoder.decode()?;
+		let metadata = match decoder.info() {
+			Some(metadata) => metadata,
+			None => return Err(Error::InvalidAsset("JPEG metadata is missing".to_string())),
+		};
+		let (width, height) = (metadata.width as u32, metadata.height as u32);
+
+		if pixels.len() != width as usize * height as usize * metadata.pixel_format.pixel_bytes() {
+			return Err(Error::InvalidAsset("JPEG pixels don't match the image size".to_string()));
+		}
+
+		let data = match metadata.pixel_format {
+			jpeg_decoder::PixelFormat::L8 => convert_gray_to_rgba(&pixels),
+			jpeg_decoder::PixelFormat::RGB24 => convert_rgb_to_rgba(&pixels),
+			jpeg_decoder::PixelFormat::CMYK32 => convert_cmyk_to_rgba(&pixels),
+		};
+
+		Ok(Texture::new(
+			width,
+			height,
+			format,
+			data,
+		))
+	}
+
+	// For images embedded in other files, for example glTF
+	pub fn decode_with_mime_type<R: std::io::Read>(
+		reader: R,
+		mime_type: &str,
+		format: TextureFormat,
+	) -> Result<Texture> {
+		match mime_type {
+			"image/png" => Self::decode_png(reader, format),
+			"image/jpeg" => Self::decode_jpg(reader, format),
+			_ => Err(Error::UnknownImageFormat(mime_type.to_string())),
+		}
+	}
+
+	pub async fn read_with_filepath(
+		file_path: &str,
+		format: TextureFormat,
+	) -> Result<Texture> {
+		let path = std::path::Path::new(file_path);
+		match path.extension().and_then(|extension| extension.to_str()) {
+			Some(str) => match str.to_lowercase().as_str() {
+				"png" => Self::decode_png(FileLoader::open(file_path).await?, format),
+				"jpg" | "jpeg" => Self::decode_jpg(FileLoader::open(file_path).await?, format),
+				_ => Err(Error::UnknownImageFormat(file_path.to_string())),
+			},
+			None => Err(Error::UnknownImageFormat(file_path.to_string())),
+		}
+	}
+
+	pub fn load_png<R: std::io::Read>(
+		pools: &mut ResourcePools,
+		reader: R,
+		format: TextureFormat,
+	) -> Result<ResourceId<Texture>> {
+		let texture = Self::decode_png(reader, format)?;
+		Ok(pools.borrow_mut::<Texture>().add(texture))
 	}
 
 	pub async fn load_png_with_filepath(
 		pools: &mut ResourcePools,
 		file_path: &str,
 		format: TextureFormat,
-	) -> ResourceId<Texture> {
-		Self::load_png(pools, FileLoader::open(file_path).await, format)
+	) -> Result<ResourceId<Texture>> {
+		Self::load_png(pools, FileLoader::open(file_path).await?, format)
 	}
 
 	pub fn load_jpg<R: std::io::Read>(
 		pools: &mut ResourcePools,
 		reader: R,
 		format: TextureFormat,
-	) -> ResourceId<Texture> {
-		let mut decoder = jpeg_decoder::Decoder::new(reader);
-		let pixels = decoder.decode().expect("failed to decode image");
-		let (width, height) = {
-			let metadata = decoder.info().unwrap();
-			(metadata.width as u32, metadata.height as u32)
-		};
-
-		// @TODO: Fix me
-		let mut data = Vec::new();
-		for y in 0..height as usize {
-			for x in 0..width as usize {
-				data.push(pixels[(y * width as usize + x) * 3 + 0]);
-				data.push(pixels[(y * width as usize + x) * 3 + 1]);
-				data.push(pixels[(y * width as usize + x) * 3 + 2]);
-				data.push(255);
-			}
-		}
-
-		pools.borrow_mut::<Texture>().add(
-			Texture::new(
-				width,
-				height,
-				format,
-				data,
-			)
-		)
+	) -> Result<ResourceId<Texture>> {
+		let texture = Self::decode_jpg(reader, format)?;
+		Ok(pools.borrow_mut::<Texture>().add(texture))
 	}
 
 	pub async fn load_jpg_with_filepath(
 		pools: &mut ResourcePools,
 		file_path: &str,
 		format: TextureFormat,
-	) -> ResourceId<Texture> {
-		Self::load_jpg(pools, FileLoader::open(file_path).await, format)
+	) -> Result<ResourceId<Texture>> {
+		Self::load_jpg(pools, FileLoader::open(file_path).await?, format)
 	}
 
 	pub async fn load_with_filepath(
 		pools: &mut ResourcePools,
 		file_path: &str,
 		format: TextureFormat,
-	) -> ResourceId<Texture> {
-		let path = std::path::Path::new(file_path);
-		// @TODO: proper error handling
-		match path.extension() {
-			Some(extension) => match extension.to_str() {
-				Some(str) => match str.to_lowercase().as_str() {
-					"png" => Self::load_png_with_filepath(pools, file_path, format).await,
-					"jpg" | "jpeg" => Self::load_jpg_with_filepath(pools, file_path, format).await,
-					_ => panic!("Unknown texture image format, {:?}", extension),
-				},
-				None => panic!("Can not detect image file format from the file path, {}", file_path),
-			},
-			None => panic!("Can not detect image file format from the file path, {}", file_path),
-		}
+	) -> Result<ResourceId<Texture>> {
+		let texture = Self::read_with_filepath(file_path, format).await?;
+		Ok(pools.borrow_mut::<Texture>().add(texture))
+	}
+}
+
+fn convert_gray_to_rgba(pixels: &[u8]) -> Vec<u8> {
+	let mut data = Vec::with_capacity(pixels.len() * 4);
+	for l in pixels.iter() {
+		data.extend_from_slice(&[*l, *l, *l, 255]);
+	}
+	data
+}
+
+fn convert_gray_alpha_to_rgba(pixels: &[u8]) -> Vec<u8> {
+	let mut data = Vec::with_capacity(pixels.len() * 2);
+	for pixel in pixels.chunks_exact(2) {
+		data.extend_from_slice(&[pixel[0], pixel[0], pixel[0], pixel[1]]);
+	}
+	data
+}
+
+fn convert_rgb_to_rgba(pixels: &[u8]) -> Vec<u8> {
+	let mut data = Vec::with_capacity(pixels.len() / 3 * 4);
+	for pixel in pixels.chunks_exact(3) {
+		data.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+	}
+	data
+}
+
+// Naive conversion without color profile
+fn convert_cmyk_to_rgba(pixels: &[u8]) -> Vec<u8> {
+	let mut data = Vec::with_capacity(pixels.len());
+	for pixel in pixels.chunks_exact(4) {
+		let k = 255 - pixel[3] as u32;
+		data.extend_from_slice(&[
+			((255 - pixel[0] as u32) * k / 255) as u8,
+			((255 - pixel[1] as u32) * k / 255) as u8,
+			((255 - pixel[2] as u32) * k / 255) as u8,
+			255,
+		]);
 	}
+	data
 }
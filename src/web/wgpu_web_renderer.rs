@@ -1,8 +1,11 @@
 use std::ops::{Deref, DerefMut};
 use winit::window::Window;
-use crate::renderer::wgpu_renderer::{
-	WGPURenderer,
-	WGPURendererOptions,
+use crate::{
+	error::error::Result,
+	renderer::wgpu_renderer::{
+		WGPURenderer,
+		WGPURendererOptions,
+	},
 };
 
 pub struct WGPUWebRenderer {
@@ -30,11 +33,11 @@ impl WGPUWebRenderer {
 		window: &Window,
 		canvas: web_sys::HtmlCanvasElement,
 		options: WGPURendererOptions,
-	) -> WGPUWebRenderer {
-		WGPUWebRenderer {
+	) -> Result<WGPUWebRenderer> {
+		Ok(WGPUWebRenderer {
 			canvas: canvas,
-			renderer: WGPURenderer::new(window, options).await,
-		}
+			renderer: WGPURenderer::new(window, options).await?,
+		})
 	}
 
 	pub fn borrow_canvas(&self) -> &web_sys::HtmlCanvasElement {
@@ -0,0 +1,304 @@
+use wgpu_rust_renderer::{
+	error::error::Error,
+	geometry::{
+		attribute::{
+			Attribute,
+			AttributeData,
+			AttributeType,
+		},
+		geometry::Geometry,
+		index::{
+			Index,
+			IndexData,
+			IndexFormat,
+		},
+		interleaved_buffer::{
+			InterleavedAttribute,
+			InterleavedBuffer,
+		},
+		vertex_layout::VertexLayout,
+	},
+	resource::resource::ResourcePools,
+	scene::{
+		instanced_mesh::InstancedMesh,
+		mesh::Mesh,
+	},
+	utils::{
+		geometry_helper::GeometryHelper,
+		material_helper::MaterialHelper,
+	},
+};
+
+#[test]
+fn index_format() {
+	let index = Index::from_u32(vec![0, 1, 65535]);
+	assert!(matches!(index.get_format(), IndexFormat::Uint16));
+	assert!(matches!(index.borrow_data(), IndexData::Uint16(data) if data == &vec![0, 1, 65535]));
+
+	let index = Index::from_u32(vec![0, 1, 65536]);
+	assert!(matches!(index.get_format(), IndexFormat::Uint32));
+	assert!(matches!(index.borrow_data(), IndexData::Uint32(data) if data == &vec![0, 1, 65536]));
+	assert_eq!(index.get_count(), 3);
+
+	let mut index = Index::new(vec![0, 1, 2]);
+	*index.borrow_data_mut() = IndexData::Uint32(vec![0, 1, 2, 70000]);
+	assert!(matches!(index.get_format(), IndexFormat::Uint32));
+	assert_eq!(index.get_count(), 4);
+	assert_eq!(index.get_version(), 1);
+}
+
+#[test]
+fn vertex_layout() {
+	let mut pools = ResourcePools::new();
+	let pool = pools.borrow_mut::<Attribute>();
+	let mut geometry = Geometry::new();
+	geometry.set_attribute("position", pool.add(Attribute::new(vec![0.0; 9], 3)));
+	geometry.set_attribute("color", pool.add(Attribute::new(vec![0.0; 12], 4)));
+	geometry.set_attribute("my-value", pool.add(Attribute::new(vec![0.0; 3], 1)));
+
+	let layout = VertexLayout::from_geometry(&geometry, &pools);
+	let names = layout.borrow_attributes()
+		.iter()
+		.map(|attribute| attribute.borrow_name().as_str())
+		.collect::<Vec<_>>();
+	assert_eq!(names, vec!["color", "my-value", "position"]);
+	assert_eq!(layout.find("color").unwrap().get_item_size(), 4);
+	assert_eq!(layout.find("my-value").unwrap().borrow_variable_name(), "a_my_value");
+	assert!(layout.find("normal").is_none());
+
+	// Same attributes, same layout
+	assert!(layout == VertexLayout::from_geometry(&geometry, &pools));
+
+	geometry.remove_attribute("color");
+	assert!(layout != VertexLayout::from_geometry(&geometry, &pools));
+
+	// Variable names are valid and unique in WGSL
+	let pool = pools.borrow_mut::<Attribute>();
+	geometry.set_attribute("loop", pool.add(Attribute::new(vec![0.0; 3], 1)));
+	geometry.set_attribute("0x", pool.add(Attribute::new(vec![0.0; 3], 1)));
+	geometry.set_attribute("my_value", pool.add(Attribute::new(vec![0.0; 3], 1)));
+	let layout = VertexLayout::from_geometry(&geometry, &pools);
+	let variable_names = layout.borrow_attributes()
+		.iter()
+		.map(|attribute| attribute.borrow_variable_name().as_str())
+		.collect::<Vec<_>>();
+	assert_eq!(variable_names, vec!["a_0x", "a_loop", "a_my_value", "a_my_value_1", "a_position"]);
+}
+
+// Attributes the shaders don't read are dropped to fit in the limits
+#[test]
+fn vertex_layout_limits() {
+	let mut pools = ResourcePools::new();
+	let pool = pools.borrow_mut::<Attribute>();
+	let mut geometry = Geometry::new();
+	for name in ["a", "b", "normal", "position", "uv", "z"].iter() {
+		geometry.set_attribute(name, pool.add(Attribute::new(vec![0.0; 9], 3)));
+	}
+
+	let mut layout = VertexLayout::from_geometry(&geometry, &pools);
+	layout.fit_limits(8, 4).unwrap();
+	let names = layout.borrow_attributes()
+		.iter()
+		.map(|attribute| attribute.borrow_name().as_str())
+		.collect::<Vec<_>>();
+	assert_eq!(names, vec!["a", "normal", "position", "uv"]);
+	assert_eq!(layout.borrow_buffers().len(), 4);
+	assert_eq!(layout.borrow_buffers()[3].borrow_attributes(), &vec![3]);
+
+	assert!(layout.fit_limits(2, 16).is_err());
+}
+
+#[test]
+fn typed_attribute() {
+	let mut pools = ResourcePools::new();
+	let pool = pools.borrow_mut::<Attribute>();
+	let joints = Attribute::new_typed(AttributeData::Uint16(vec![0; 8]), 4, false);
+	let color = Attribute::new_typed(AttributeData::Uint8(vec![0; 6]), 3, true);
+	let uv = Attribute::new_typed(AttributeData::Uint16(vec![0; 4]), 2, true);
+	let uv2 = Attribute::new_typed(AttributeData::Uint8(vec![0; 4]), 2, true);
+	assert_eq!(joints.get_count(), 2);
+	assert_eq!(color.get_count(), 2);
+	// Padded to meet the vertex format sizes and four byte strides
+	assert_eq!(joints.get_padded_item_size(), 4);
+	assert_eq!(color.get_padded_item_size(), 4);
+	assert_eq!(color.get_aligned_size(), 4);
+	assert_eq!(uv.get_padded_item_size(), 2);
+	assert_eq!(uv2.get_padded_item_size(), 2);
+	assert_eq!(uv2.get_aligned_size(), 4);
+	assert_eq!(Attribute::new(vec![0.0; 9], 3).get_padded_item_size(), 3);
+
+	let mut geometry = Geometry::new();
+	geometry.set_attribute("joints", pool.add(joints));
+	geometry.set_attribute("color", pool.add(color));
+	geometry.set_attribute("uv2", pool.add(uv2));
+	let layout = VertexLayout::from_geometry(&geometry, &pools);
+	let uv2 = layout.find("uv2").unwrap();
+	assert_eq!(uv2.get_padded_item_size(), 2);
+	assert_eq!(uv2.get_padded_size(), 2);
+	assert_eq!(layout.borrow_buffers()[2].get_stride(), 4);
+	let joints = layout.find("joints").unwrap();
+	assert!(joints.get_attribute_type() == AttributeType::Uint16);
+	assert!(joints.is_integer());
+	let color = layout.find("color").unwrap();
+	assert!(color.is_normalized());
+	assert!(!color.is_integer());
+}
+
+#[test]
+fn interleaved_layout() {
+	let mut pools = ResourcePools::new();
+	let buffer = pools.borrow_mut::<InterleavedBuffer>().add(InterleavedBuffer::new(vec![0; 60], 20));
+	let normal = pools.borrow_mut::<Attribute>().add(Attribute::new(vec![0.0; 9], 3));
+
+	let mut geometry = Geometry::new();
+	geometry.set_interleaved_attribute("position", InterleavedAttribute::new(buffer, AttributeType::Float32, 3, 0, false));
+	geometry.set_interleaved_attribute("uv", InterleavedAttribute::new(buffer, AttributeType::Uint16, 2, 12, true));
+	geometry.set_attribute("normal", normal);
+	// Doesn't fit in the stride
+	geometry.set_interleaved_attribute("color", InterleavedAttribute::new(buffer, AttributeType::Float32, 3, 12, false));
+
+	let layout = VertexLayout::from_geometry(&geometry, &pools);
+	assert!(layout.find("color").is_none());
+	assert_eq!(layout.find("uv").unwrap().get_offset(), 12);
+
+	// normal, position, uv in location order, position and uv sharing a buffer
+	let buffers = layout.borrow_buffers();
+	assert_eq!(buffers.len(), 2);
+	assert_eq!(buffers[0].borrow_attributes(), &vec![0]);
+	assert_eq!(buffers[0].get_stride(), 12);
+	assert_eq!(buffers[1].borrow_attributes(), &vec![1, 2]);
+	assert_eq!(buffers[1].get_stride(), 20);
+
+	// Replaces the interleaved one
+	geometry.set_attribute("position", normal);
+	assert!(geometry.borrow_interleaved_attribute("position").is_none());
+	assert_eq!(VertexLayout::from_geometry(&geometry, &pools).borrow_buffers().len(), 3);
+}
+
+#[test]
+fn interleave() {
+	let mut pools = ResourcePools::new();
+	let geometry_rid = GeometryHelper::create_triangle(&mut pools, 1.0, 1.0);
+	let color = pools.borrow_mut::<Attribute>().add(
+		Attribute::new_typed(AttributeData::Uint8(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]), 3, true),
+	);
+	pools.borrow_mut::<Geometry>().borrow_mut(&geometry_rid).unwrap().set_attribute("color", color);
+
+	let buffer = GeometryHelper::interleave(&mut pools, &geometry_rid).unwrap();
+
+	// color (4 bytes padded), normal (12), position (12), uv (8)
+	let buffer = pools.borrow::<InterleavedBuffer>().borrow(&buffer).unwrap();
+	assert_eq!(buffer.get_stride(), 36);
+	assert_eq!(buffer.get_count(), 3);
+	assert_eq!(&buffer.borrow_data()[36..40], &[4, 5, 6, 0]);
+
+	let geometry = pools.borrow::<Geometry>().borrow(&geometry_rid).unwrap();
+	assert!(geometry.borrow_attributes().is_empty());
+	assert_eq!(geometry.borrow_interleaved_attribute("position").unwrap().get_offset(), 16);
+	assert_eq!(geometry.borrow_interleaved_attribute("uv").unwrap().get_offset(), 28);
+
+	let layout = VertexLayout::from_geometry(geometry, &pools);
+	assert_eq!(layout.borrow_attributes().len(), 4);
+	assert_eq!(layout.borrow_buffers().len(), 1);
+	assert_eq!(layout.find("color").unwrap().get_padded_item_size(), 4);
+}
+
+#[test]
+fn draw_ranges() {
+	let mut geometry = Geometry::new();
+	assert_eq!(geometry.collect_draw_ranges(12), vec![(0, 0..12)]);
+	assert!(geometry.collect_draw_ranges(0).is_empty());
+
+	geometry.set_draw_range(3, 6);
+	assert_eq!(geometry.collect_draw_ranges(12), vec![(0, 3..9)]);
+	assert_eq!(geometry.collect_draw_ranges(6), vec![(0, 3..6)]);
+	assert!(geometry.collect_draw_ranges(2).is_empty());
+
+	// Groups are clipped by the draw range and empty ones omitted
+	geometry.add_group(0, 6, 1);
+	geometry.add_group(6, 6, 0);
+	geometry.add_group(9, 3, 2);
+	assert_eq!(geometry.collect_draw_ranges(12), vec![(1, 3..6), (0, 6..9)]);
+
+	geometry.set_draw_range(0, u32::MAX);
+	assert_eq!(geometry.collect_draw_ranges(12), vec![(1, 0..6), (0, 6..12), (2, 9..12)]);
+
+	geometry.clear_groups();
+	assert_eq!(geometry.borrow_groups().len(), 0);
+	assert_eq!(geometry.collect_draw_ranges(12), vec![(0, 0..12)]);
+}
+
+#[test]
+fn mesh_materials() {
+	let mut pools = ResourcePools::new();
+	let geometry = GeometryHelper::create_triangle(&mut pools, 1.0, 1.0);
+	let a = MaterialHelper::create_basic_material(&mut pools, &[1.0, 0.0, 0.0]);
+	let b = MaterialHelper::create_basic_material(&mut pools, &[0.0, 1.0, 0.0]);
+
+	let mut mesh = Mesh::new_with_materials(geometry, vec![a, b]).unwrap();
+	assert!(mesh.borrow_material() == &a);
+	assert!(mesh.borrow_material_at(1) == Some(&b));
+	assert!(mesh.borrow_material_at(2).is_none());
+
+	mesh.set_material(b);
+	assert!(mesh.borrow_materials() == &vec![b]);
+
+	assert!(matches!(mesh.set_materials(Vec::new()), Err(Error::NoMaterial)));
+	assert!(mesh.borrow_materials() == &vec![b]);
+	assert!(matches!(Mesh::new_with_materials(geometry, Vec::new()), Err(Error::NoMaterial)));
+}
+
+#[test]
+fn instanced_mesh() {
+	let mut pools = ResourcePools::new();
+	let geometry_rid = GeometryHelper::create_triangle(&mut pools, 1.0, 1.0);
+	let material = MaterialHelper::create_basic_material(&mut pools, &[1.0, 1.0, 1.0]);
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry_rid, material));
+
+	let mut instanced_mesh = InstancedMesh::new(mesh, 2);
+	assert_eq!(instanced_mesh.get_count(), 2);
+	assert!(instanced_mesh.borrow_colors().is_none());
+
+	let mut matrix = [0.0; 16];
+	matrix[0] = 2.0;
+	instanced_mesh.set_matrix_at(1, &matrix);
+	instanced_mesh.set_color_at(0, &[1.0, 0.0, 0.0]);
+	assert_eq!(instanced_mesh.borrow_matrices()[1][0], 2.0);
+	assert_eq!(instanced_mesh.borrow_colors().unwrap(), &vec![[1.0, 0.0, 0.0], [1.0; 3]]);
+
+	instanced_mesh.set_count(3);
+	assert_eq!(instanced_mesh.borrow_matrices()[2][15], 1.0);
+	assert_eq!(instanced_mesh.borrow_colors().unwrap().len(), 3);
+	assert_eq!(instanced_mesh.get_version(), 3);
+
+	// Instance inputs follow the geometry ones in a buffer of their own
+	let geometry = pools.borrow::<Geometry>().borrow(&geometry_rid).unwrap();
+	let mut layout = VertexLayout::from_geometry(geometry, &pools);
+	layout.add_instance_attributes(true);
+	assert_eq!(layout.borrow_attributes().len(), 8);
+	assert_eq!(layout.find("instance_matrix0").unwrap().get_offset(), 0);
+	assert_eq!(layout.find("instance_color").unwrap().get_offset(), 64);
+	let buffers = layout.borrow_buffers();
+	assert_eq!(buffers.len(), 4);
+	assert!(!buffers[2].is_instanced());
+	assert!(buffers[3].is_instanced());
+	assert_eq!(buffers[3].borrow_attributes(), &vec![3, 4, 5, 6, 7]);
+	assert_eq!(buffers[3].get_stride(), 76);
+
+	// Instance inputs are in the limits and kept
+	let pool = pools.borrow_mut::<Attribute>();
+	let mut geometry = Geometry::new();
+	for name in ["a", "b", "position"].iter() {
+		geometry.set_attribute(name, pool.add(Attribute::new(vec![0.0; 9], 3)));
+	}
+	let mut layout = VertexLayout::from_geometry(&geometry, &pools);
+	layout.add_instance_attributes(true);
+	layout.fit_limits(8, 7).unwrap();
+	assert_eq!(layout.borrow_attributes().len(), 7);
+	assert!(layout.find("a").is_some());
+	assert!(layout.find("b").is_none());
+	assert_eq!(layout.borrow_buffers()[2].borrow_attributes(), &vec![2, 3, 4, 5, 6]);
+	layout.fit_limits(2, 16).unwrap();
+	assert!(layout.find("a").is_none());
+	assert!(layout.fit_limits(1, 16).is_err());
+}
@@ -0,0 +1,365 @@
+use wgpu_rust_renderer::{
+	geometry::{
+		attribute::{
+			Attribute,
+			AttributeData,
+		},
+		geometry::Geometry,
+		index::{
+			Index,
+			IndexData,
+		},
+	},
+	material::material::Material,
+	resource::resource::ResourcePools,
+	scene::{
+		mesh::Mesh,
+		node::Node,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
+	},
+	texture::{
+		sampler::Sampler,
+		texture::Texture,
+	},
+	utils::gltf_loader::GltfLoader,
+};
+
+// Writes a file to a temporary directory and returns the directory
+fn write_asset(name: &str, contents: &[u8]) -> String {
+	let dir = std::env::temp_dir().join(format!("wgpu_rust_renderer_{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	std::fs::write(dir.join(name), contents).unwrap();
+	dir.to_str().unwrap().to_string() + "/"
+}
+
+// Positions of a triangle
+fn triangle_bytes() -> Vec<u8> {
+	[0.0_f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+		.iter()
+		.flat_map(|value| value.to_le_bytes().to_vec())
+		.collect()
+}
+
+fn assert_near(a: f32, b: f32) {
+	assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+}
+
+#[tokio::test]
+async fn node_hierarchy() {
+	// Root translated by matrix, child rotated 90 degrees around Y by
+	// quaternion and translated along X, grandchild translated along X
+	let path = write_asset("hierarchy.gltf", br#"{
+		"asset": {"version": "2.0"},
+		"scene": 0,
+		"scenes": [{"nodes": [0, 3]}],
+		"nodes": [
+			{"children": [1], "matrix": [1,0,0,0, 0,1,0,0, 0,0,1,0, 0,5,0,1]},
+			{"children": [2], "translation": [1,0,0], "rotation": [0,0.70710678,0,0.70710678]},
+			{"translation": [1,0,0], "scale": [2,2,2]},
+			{}
+		]
+	}"#);
+
+	let mut pools = ResourcePools::new();
+	let scene = pools.borrow_mut::<Scene>().add(Scene::new());
+	let roots = GltfLoader::load_gltf(&mut pools, &scene, &path, "hierarchy.gltf").await.unwrap();
+	assert_eq!(roots.len(), 2);
+
+	for root in roots.iter() {
+		pools.borrow_mut::<Scene>().borrow_mut(&scene).unwrap().add_node(root);
+	}
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+
+	let nodes = pools.borrow::<Node>();
+	let root = nodes.borrow(&roots[0]).unwrap();
+	assert_eq!(root.borrow_children().len(), 1);
+	assert_near(root.borrow_position()[1], 5.0);
+
+	let child = nodes.borrow(&root.borrow_children()[0]).unwrap();
+	assert!(child.borrow_parent() == Some(&roots[0]));
+	assert_near(child.borrow_quaternion()[1], 0.70710678);
+	assert_near(child.borrow_quaternion()[3], 0.70710678);
+
+	let grandchild = nodes.borrow(&child.borrow_children()[0]).unwrap();
+	assert_near(grandchild.borrow_scale()[0], 2.0);
+
+	// (1, 5, 0) + rotated (1, 0, 0) = (1, 5, -1)
+	let world_matrix = grandchild.borrow_world_matrix();
+	assert_near(world_matrix[12], 1.0);
+	assert_near(world_matrix[13], 5.0);
+	assert_near(world_matrix[14], -1.0);
+
+	assert!(nodes.borrow(&roots[1]).unwrap().borrow_children().is_empty());
+}
+
+#[tokio::test]
+async fn mesh_primitives() {
+	write_asset("triangle.bin", &triangle_bytes());
+	let path = write_asset("primitives.gltf", br#"{
+		"asset": {"version": "2.0"},
+		"buffers": [{"uri": "triangle.bin", "byteLength": 36}],
+		"bufferViews": [{"buffer": 0, "byteLength": 36}],
+		"accessors": [{
+			"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+			"min": [0, 0, 0], "max": [1, 1, 0]
+		}],
+		"meshes": [{"primitives": [
+			{"attributes": {"POSITION": 0}},
+			{"attributes": {"POSITION": 0}}
+		]}],
+		"nodes": [{"mesh": 0}],
+		"scenes": [{"nodes": [0]}]
+	}"#);
+
+	let mut pools = ResourcePools::new();
+	let scene_rid = pools.borrow_mut::<Scene>().add(Scene::new());
+	let roots = GltfLoader::load_gltf(&mut pools, &scene_rid, &path, "primitives.gltf").await.unwrap();
+
+	// A mesh per primitive
+	let scene = pools.borrow::<Scene>().borrow(&scene_rid).unwrap();
+	let meshes = scene.borrow_all_assigned_to::<Mesh>(&roots[0]);
+	assert_eq!(meshes.len(), 2);
+	assert!(meshes[0] != meshes[1]);
+}
+
+// 1x1 red PNG
+fn png_bytes() -> Vec<u8> {
+	let mut bytes = Vec::new();
+	{
+		let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+		let mut writer = encoder.write_header().unwrap();
+		writer.write_image_data(&[255, 0, 0, 255]).unwrap();
+	}
+	bytes
+}
+
+fn pad(mut data: Vec<u8>, value: u8) -> Vec<u8> {
+	while data.len() % 4 != 0 {
+		data.push(value);
+	}
+	data
+}
+
+fn glb_bytes(json: &str, bin: Vec<u8>) -> Vec<u8> {
+	let json = pad(json.as_bytes().to_vec(), b' ');
+	let bin = pad(bin, 0);
+	let mut bytes = Vec::new();
+	bytes.extend_from_slice(b"glTF");
+	bytes.extend_from_slice(&2_u32.to_le_bytes());
+	bytes.extend_from_slice(&(12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+	bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+	bytes.extend_from_slice(b"JSON");
+	bytes.extend_from_slice(&json);
+	bytes.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+	bytes.extend_from_slice(b"BIN\0");
+	bytes.extend_from_slice(&bin);
+	bytes
+}
+
+#[tokio::test]
+async fn glb_with_image_in_buffer_view() {
+	let png = png_bytes();
+	let mut bin = triangle_bytes();
+	bin.extend_from_slice(&png);
+
+	let json = format!(r#"{{
+		"asset": {{"version": "2.0"}},
+		"buffers": [{{"byteLength": {buffer_length}}}],
+		"bufferViews": [
+			{{"buffer": 0, "byteLength": 36}},
+			{{"buffer": 0, "byteOffset": 36, "byteLength": {png_length}}}
+		],
+		"accessors": [{{
+			"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+			"min": [0, 0, 0], "max": [1, 1, 0]
+		}}],
+		"images": [{{"bufferView": 1, "mimeType": "image/png"}}],
+		"textures": [{{"source": 0}}],
+		"materials": [{{"pbrMetallicRoughness": {{"baseColorTexture": {{"index": 0}}}}}}],
+		"meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}],
+		"nodes": [{{"mesh": 0}}],
+		"scenes": [{{"nodes": [0]}}]
+	}}"#, buffer_length = bin.len(), png_length = png.len());
+
+	let path = write_asset("triangle.glb", &glb_bytes(&json, bin));
+
+	let mut pools = ResourcePools::new();
+	let scene = pools.borrow_mut::<Scene>().add(Scene::new());
+	let roots = GltfLoader::load_gltf(&mut pools, &scene, &path, "triangle.glb").await.unwrap();
+	assert_eq!(roots.len(), 1);
+
+	let textures = pools.borrow::<Texture>();
+	assert_eq!(textures.len(), 1);
+	let (_, texture) = textures.iter().next().unwrap();
+	assert_eq!(texture.get_width(), 1);
+	assert_eq!(&texture.borrow_texels()[..4], &[255, 0, 0, 255]);
+}
+
+#[tokio::test]
+async fn data_uri() {
+	let path = write_asset("data_uri.gltf", format!(r#"{{
+		"asset": {{"version": "2.0"}},
+		"buffers": [{{
+			"uri": "data:application/octet-stream;base64,{}",
+			"byteLength": 36
+		}}],
+		"bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+		"accessors": [{{
+			"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+			"min": [0, 0, 0], "max": [1, 1, 0]
+		}}],
+		"images": [{{"uri": "data:image/png;base64,{}"}}],
+		"meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+		"nodes": [{{"mesh": 0}}],
+		"scenes": [{{"nodes": [0]}}]
+	}}"#, base64::encode(triangle_bytes()), base64::encode(png_bytes())).as_bytes());
+
+	let asset = GltfLoader::read_gltf(&path, "data_uri.gltf").await.unwrap();
+	let mut pools = ResourcePools::new();
+	let scene_rid = pools.borrow_mut::<Scene>().add(Scene::new());
+	let roots = GltfLoader::build(&mut pools, &scene_rid, &asset).unwrap();
+
+	let scene = pools.borrow::<Scene>().borrow(&scene_rid).unwrap();
+	assert_eq!(scene.borrow_all_assigned_to::<Mesh>(&roots[0]).len(), 1);
+}
+
+#[tokio::test]
+async fn accessors() {
+	let mut bin = Vec::new();
+	// Interleaved f32 positions and normalized u16 uvs
+	for (position, uv) in [
+		([0.0_f32, 0.0, 0.0], [0_u16, 0]),
+		([1.0, 0.0, 0.0], [65535, 0]),
+		([0.0, 1.0, 0.0], [0, 65535]),
+	].iter() {
+		for value in position.iter() {
+			bin.extend_from_slice(&value.to_le_bytes());
+		}
+		for value in uv.iter() {
+			bin.extend_from_slice(&value.to_le_bytes());
+		}
+	}
+	// u8 indices at 48
+	bin.extend_from_slice(&[0, 1, 2, 0]);
+	// Sparse u8 index at 52 and f32 value at 56
+	bin.extend_from_slice(&[1, 0, 0, 0]);
+	for value in [0.0_f32, 0.0, 1.0].iter() {
+		bin.extend_from_slice(&value.to_le_bytes());
+	}
+
+	write_asset("accessors.bin", &bin);
+	let path = write_asset("accessors.gltf", br#"{
+		"asset": {"version": "2.0"},
+		"buffers": [{"uri": "accessors.bin", "byteLength": 68}],
+		"bufferViews": [
+			{"buffer": 0, "byteLength": 48, "byteStride": 16},
+			{"buffer": 0, "byteOffset": 48, "byteLength": 3},
+			{"buffer": 0, "byteOffset": 52, "byteLength": 1},
+			{"buffer": 0, "byteOffset": 56, "byteLength": 12}
+		],
+		"accessors": [
+			{
+				"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+				"min": [0, 0, 0], "max": [1, 1, 0]
+			},
+			{
+				"bufferView": 0, "byteOffset": 12, "componentType": 5123,
+				"normalized": true, "count": 3, "type": "VEC2"
+			},
+			{"bufferView": 1, "componentType": 5121, "count": 3, "type": "SCALAR"},
+			{
+				"componentType": 5126, "count": 3, "type": "VEC3",
+				"sparse": {
+					"count": 1,
+					"indices": {"bufferView": 2, "componentType": 5121},
+					"values": {"bufferView": 3}
+				}
+			}
+		],
+		"meshes": [{"primitives": [{
+			"attributes": {"POSITION": 0, "TEXCOORD_0": 1, "NORMAL": 3},
+			"indices": 2
+		}]}],
+		"nodes": [{"mesh": 0}],
+		"scenes": [{"nodes": [0]}]
+	}"#);
+
+	let mut pools = ResourcePools::new();
+	let scene_rid = pools.borrow_mut::<Scene>().add(Scene::new());
+	let roots = GltfLoader::load_gltf(&mut pools, &scene_rid, &path, "accessors.gltf").await.unwrap();
+
+	let scene = pools.borrow::<Scene>().borrow(&scene_rid).unwrap();
+	let mesh = scene.borrow_assigned_to::<Mesh>(&roots[0]).unwrap();
+	let mesh = pools.borrow::<Mesh>().borrow(mesh).unwrap();
+	let geometry = pools.borrow::<Geometry>().borrow(mesh.borrow_geometry()).unwrap();
+	let attributes = pools.borrow::<Attribute>();
+
+	let position = attributes.borrow(geometry.borrow_attribute("position").unwrap()).unwrap();
+	assert_eq!(position.get_item_size(), 3);
+	assert!(matches!(position.borrow_data(), AttributeData::Float32(data)
+		if data == &vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]));
+
+	let uv = attributes.borrow(geometry.borrow_attribute("uv").unwrap()).unwrap();
+	assert_eq!(uv.get_item_size(), 2);
+	assert!(uv.is_normalized());
+	assert!(matches!(uv.borrow_data(), AttributeData::Uint16(data)
+		if data == &vec![0, 0, 65535, 0, 0, 65535]));
+
+	let normal = attributes.borrow(geometry.borrow_attribute("normal").unwrap()).unwrap();
+	assert!(matches!(normal.borrow_data(), AttributeData::Float32(data)
+		if data == &vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]));
+
+	let index = pools.borrow::<Index>().borrow(geometry.borrow_index().unwrap()).unwrap();
+	assert!(matches!(index.borrow_data(), IndexData::Uint16(data) if data == &vec![0, 1, 2]));
+}
+
+// Materials, textures and samplers referred to more than once are built once
+#[tokio::test]
+async fn shared_resources() {
+	let path = write_asset("shared.gltf", format!(r#"{{
+		"asset": {{"version": "2.0"}},
+		"buffers": [{{
+			"uri": "data:application/octet-stream;base64,{}",
+			"byteLength": 36
+		}}],
+		"bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+		"accessors": [{{
+			"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+			"min": [0, 0, 0], "max": [1, 1, 0]
+		}}],
+		"images": [{{"uri": "data:image/png;base64,{}"}}],
+		"samplers": [{{}}],
+		"textures": [{{"source": 0, "sampler": 0}}],
+		"materials": [{{
+			"pbrMetallicRoughness": {{
+				"baseColorTexture": {{"index": 0}},
+				"metallicRoughnessTexture": {{"index": 0}}
+			}},
+			"emissiveTexture": {{"index": 0}}
+		}}],
+		"meshes": [
+			{{"primitives": [
+				{{"attributes": {{"POSITION": 0}}, "material": 0}},
+				{{"attributes": {{"POSITION": 0}}, "material": 0}}
+			]}},
+			{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}
+		],
+		"nodes": [{{"mesh": 0}}, {{"mesh": 1}}],
+		"scenes": [{{"nodes": [0, 1]}}]
+	}}"#, base64::encode(triangle_bytes()), base64::encode(png_bytes())).as_bytes());
+
+	let mut pools = ResourcePools::new();
+	let scene = pools.borrow_mut::<Scene>().add(Scene::new());
+	GltfLoader::load_gltf(&mut pools, &scene, &path, "shared.gltf").await.unwrap();
+
+	assert_eq!(pools.borrow::<Mesh>().len(), 3);
+	assert_eq!(pools.borrow::<Material>().len(), 1);
+	// sRGB base color and emissive, and linear metallic roughness
+	assert_eq!(pools.borrow::<Texture>().len(), 2);
+	assert_eq!(pools.borrow::<Sampler>().len(), 1);
+}
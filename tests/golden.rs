@@ -0,0 +1,629 @@
+// Golden image regression tests.
+//
+// Renders the scenes of the examples offscreen and compares them against
+// the reference images in tests/golden/. A missing reference image fails
+// the test. With UPDATE_GOLDEN environment variable set, the rendered image
+// is written as the new reference instead. On mismatch the rendered image
+// and a diff image are written to target/golden/.
+//
+// The scenes are rendered on the fallback (software) adapter so that the
+// results don't depend on the GPU of the machine. The tests pass without
+// comparing anything if no adapter is found.
+
+use std::{
+	fs::File,
+	io::BufWriter,
+	path::PathBuf,
+};
+
+use wgpu_rust_renderer::{
+	error::error::Error,
+	geometry::{
+		attribute::{
+			Attribute,
+			AttributeData,
+		},
+		geometry::Geometry,
+	},
+	material::{
+		material::{
+			Material,
+			Side,
+		},
+		node::node::MaterialNode,
+	},
+	math::{
+		color::Color,
+		matrix4::Matrix4,
+		vector3::Vector3,
+	},
+	renderer::wgpu_renderer::{
+		WGPURenderer,
+		WGPURendererOptions,
+	},
+	resource::resource::{
+		ResourceId,
+		ResourcePools,
+	},
+	scene::{
+		camera::PerspectiveCamera,
+		instanced_mesh::InstancedMesh,
+		mesh::Mesh,
+		node::Node,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
+	},
+	utils::{
+		geometry_helper::GeometryHelper,
+		material_helper::MaterialHelper,
+	},
+};
+
+#[path = "../examples/scenes/mod.rs"]
+mod scenes;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 256;
+
+// Max allowed difference per color channel
+const TOLERANCE: u8 = 8;
+
+fn golden_path(name: &str) -> PathBuf {
+	let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+		.join("tests")
+		.join("golden");
+	std::fs::create_dir_all(&dir).unwrap();
+	dir.join(format!("{}.png", name))
+}
+
+fn output_path(name: &str) -> PathBuf {
+	let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+		.join("target")
+		.join("golden");
+	std::fs::create_dir_all(&dir).unwrap();
+	dir.join(name)
+}
+
+fn options() -> WGPURendererOptions {
+	WGPURendererOptions {
+		force_fallback_adapter: true,
+		..WGPURendererOptions::default()
+	}
+}
+
+fn read_png(path: &PathBuf) -> (u32, u32, Vec<u8>) {
+	let decoder = png::Decoder::new(File::open(path).unwrap());
+	let mut reader = decoder.read_info().unwrap();
+	let mut buf = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut buf).unwrap();
+	assert_eq!(info.color_type, png::ColorType::Rgba);
+	(info.width, info.height, buf)
+}
+
+fn write_png(path: &PathBuf, width: u32, height: u32, pixels: &[u8]) {
+	let mut encoder = png::Encoder::new(
+		BufWriter::new(File::create(path).unwrap()),
+		width,
+		height,
+	);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+}
+
+const ASPECT: f32 = WIDTH as f32 / HEIGHT as f32;
+
+// Turns the objects so that more than one face is visible
+fn rotate(pools: &mut ResourcePools, objects: &[ResourceId<Node>], x: f32, y: f32) {
+	for object in objects.iter() {
+		let node = pools.borrow_mut::<Node>().borrow_mut(object).unwrap();
+		Vector3::set(node.borrow_rotation_mut(), x, y, 0.0);
+	}
+}
+
+// Box faces alternating two materials, the last face cut by the draw range
+fn create_groups_scene(
+	pools: &mut ResourcePools,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>) {
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_box(pools, 1.0, 1.0, 1.0);
+	{
+		let geometry = pools.borrow_mut::<Geometry>().borrow_mut(&geometry).unwrap();
+		for face in 0..6 {
+			geometry.add_group(face * 6, 6, face as usize % 2);
+		}
+		geometry.set_draw_range(0, 33);
+	}
+
+	let red = MaterialHelper::create_basic_material(
+		pools,
+		Color::set(&mut Color::create(), 1.0, 0.0, 0.0),
+	);
+	let blue = MaterialHelper::create_basic_material(
+		pools,
+		Color::set(&mut Color::create(), 0.0, 0.0, 1.0),
+	);
+
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new_with_materials(geometry, vec![red, blue]).unwrap());
+	let mut node = Node::new();
+	Vector3::set(node.borrow_rotation_mut(), 0.5, 0.5, 0.0);
+	let node = pools.borrow_mut::<Node>().add(node);
+	scene.add_node(&node);
+	scene.assign(&node, &mesh);
+
+	let (camera, camera_node) = scenes::create_camera(pools, ASPECT, 3.0);
+	scene.add_node(&camera_node);
+	scene.assign(&camera_node, &camera);
+
+	(pools.borrow_mut::<Scene>().add(scene), camera)
+}
+
+// A grid of cubes in one draw call with colors per instance
+fn create_instancing_scene(
+	pools: &mut ResourcePools,
+) -> (ResourceId<Scene>, ResourceId<PerspectiveCamera>, ResourceId<Node>) {
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_box(pools, 0.5, 0.5, 0.5);
+	let material = MaterialHelper::create_basic_material(
+		pools,
+		Color::set(&mut Color::create(), 1.0, 1.0, 1.0),
+	);
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+
+	let mut instanced_mesh = InstancedMesh::new(mesh, 9);
+	for i in 0..9 {
+		let mut matrix = Matrix4::create();
+		matrix[12] = (i % 3) as f32 - 1.0;
+		matrix[13] = (i / 3) as f32 - 1.0;
+		instanced_mesh.set_matrix_at(i, &matrix);
+		instanced_mesh.set_color_at(i, &[(i % 3) as f32 * 0.5, (i / 3) as f32 * 0.5, 1.0]);
+	}
+	let instanced_mesh = pools.borrow_mut::<InstancedMesh>().add(instanced_mesh);
+
+	let mut node = Node::new();
+	Vector3::set(node.borrow_rotation_mut(), 0.3, 0.3, 0.0);
+	let node = pools.borrow_mut::<Node>().add(node);
+	scene.add_node(&node);
+	scene.assign(&node, &instanced_mesh);
+
+	let (camera, camera_node) = scenes::create_camera(pools, ASPECT, 4.0);
+	scene.add_node(&camera_node);
+	scene.assign(&camera_node, &camera);
+
+	(pools.borrow_mut::<Scene>().add(scene), camera, node)
+}
+
+// None if no adapter is found. Other errors fail the test.
+async fn create_renderer() -> Option<WGPURenderer> {
+	match WGPURenderer::new_offscreen(WIDTH as f64, HEIGHT as f64, options()).await {
+		Ok(renderer) => Some(renderer),
+		Err(Error::NoAdapter) => {
+			eprintln!("No adapter is found. Skips the golden image test.");
+			None
+		},
+		Err(e) => panic!("Failed to create renderer: {}", e),
+	}
+}
+
+async fn render(
+	pools: &mut ResourcePools,
+	scene: &ResourceId<Scene>,
+	camera: &ResourceId<PerspectiveCamera>,
+) -> Option<Vec<u8>> {
+	let mut renderer = create_renderer().await?;
+
+	SceneExecutor::update_matrices(pools, scene).unwrap();
+
+	renderer.render(pools, scene, camera).unwrap();
+	Some(renderer.read_pixels().await.unwrap().unwrap())
+}
+
+fn compare(name: &str, pixels: &[u8]) {
+	let path = golden_path(name);
+
+	if std::env::var("UPDATE_GOLDEN").is_ok() {
+		write_png(&path, WIDTH, HEIGHT, pixels);
+		eprintln!("Wrote reference image {:?}", path);
+		return;
+	}
+
+	assert!(
+		path.exists(),
+		"Reference image {:?} is missing. Run with UPDATE_GOLDEN=1 to write it.",
+		path,
+	);
+
+	let (width, height, expected) = read_png(&path);
+	assert_eq!((width, height), (WIDTH, HEIGHT), "Reference image size mismatch: {}", name);
+
+	let mut diff = Vec::with_capacity(expected.len());
+	let mut mismatches = 0;
+
+	for (actual, expected) in pixels.chunks(4).zip(expected.chunks(4)) {
+		let max_delta = actual
+			.iter()
+			.zip(expected.iter())
+			.map(|(a, e)| if a > e { a - e } else { e - a })
+			.max()
+			.unwrap();
+
+		if max_delta > TOLERANCE {
+			mismatches += 1;
+			diff.extend_from_slice(&[255, 0, 0, 255]);
+		} else {
+			// Dimmed actual pixel so that the mismatches stand out
+			diff.extend_from_slice(&[actual[0] / 4, actual[1] / 4, actual[2] / 4, 255]);
+		}
+	}
+
+	if mismatches > 0 {
+		write_png(&output_path(&format!("{}.actual.png", name)), WIDTH, HEIGHT, pixels);
+		write_png(&output_path(&format!("{}.diff.png", name)), WIDTH, HEIGHT, &diff);
+		panic!(
+			"{} pixels differ from the reference image {:?} more than the tolerance {}. See target/golden/{}.diff.png",
+			mismatches, path, TOLERANCE, name,
+		);
+	}
+}
+
+#[tokio::test]
+async fn triangle() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, _) = scenes::create_triangle_scene(&mut pools, ASPECT);
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("triangle", &pixels);
+	}
+}
+
+#[tokio::test]
+async fn cube() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, objects) = scenes::create_cube_scene(&mut pools, ASPECT);
+	rotate(&mut pools, &objects, 0.5, 0.5);
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("cube", &pixels);
+	}
+}
+
+// Same image as cube
+#[tokio::test]
+async fn interleaved() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, objects) = scenes::create_cube_scene(&mut pools, ASPECT);
+	rotate(&mut pools, &objects, 0.5, 0.5);
+	let geometries = pools.borrow::<Geometry>().iter().map(|(rid, _)| rid).collect::<Vec<_>>();
+	for geometry in geometries.iter() {
+		assert!(GeometryHelper::interleave(&mut pools, geometry).is_some());
+	}
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("cube", &pixels);
+	}
+}
+
+#[tokio::test]
+async fn groups() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera) = create_groups_scene(&mut pools);
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("groups", &pixels);
+	}
+}
+
+#[tokio::test]
+async fn instancing() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, _) = create_instancing_scene(&mut pools);
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("instancing", &pixels);
+	}
+}
+
+#[tokio::test]
+async fn texture() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, _) = scenes::create_texture_scene(&mut pools, ASPECT).await;
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("texture", &pixels);
+	}
+}
+
+// 8-bit x2 uvs are read with the x2 vertex formats, in separate and
+// interleaved buffers. Box uvs are 0 or 1 so they render the same.
+#[tokio::test]
+async fn normalized_uv() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, _) = scenes::create_texture_scene(&mut pools, ASPECT).await;
+	let geometries = pools.borrow::<Geometry>().iter().map(|(rid, _)| rid).collect::<Vec<_>>();
+	for geometry in geometries.iter() {
+		let uv = *pools.borrow::<Geometry>().borrow(geometry).unwrap().borrow_attribute("uv").unwrap();
+		let data = match pools.borrow::<Attribute>().borrow(&uv).unwrap().borrow_data() {
+			AttributeData::Float32(data) => data.iter().map(|v| (v * 255.0).round() as u8).collect::<Vec<_>>(),
+			_ => unreachable!(),
+		};
+		let uv = pools.borrow_mut::<Attribute>().add(
+			Attribute::new_typed(AttributeData::Uint8(data), 2, true),
+		);
+		pools.borrow_mut::<Geometry>().borrow_mut(geometry).unwrap().set_attribute("uv", uv);
+	}
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("texture", &pixels);
+	}
+
+	for geometry in geometries.iter() {
+		assert!(GeometryHelper::interleave(&mut pools, geometry).is_some());
+	}
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("texture", &pixels);
+	}
+}
+
+#[tokio::test]
+async fn pbr() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, objects) = scenes::create_pbr_scene(&mut pools, ASPECT);
+	rotate(&mut pools, &objects, 0.5, 0.5);
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("pbr", &pixels);
+	}
+}
+
+#[tokio::test]
+async fn gltf() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, _) = scenes::create_gltf_scene(&mut pools, ASPECT).await;
+	if let Some(pixels) = render(&mut pools, &scene, &camera).await {
+		compare("gltf", &pixels);
+	}
+}
+
+// Edits after the first render must reach the GPU
+#[tokio::test]
+async fn update() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, objects) = scenes::create_triangle_scene(&mut pools, ASPECT);
+
+	let mesh = pools.borrow::<Scene>()
+		.borrow(&scene)
+		.unwrap()
+		.borrow_all_assigned_to::<Mesh>(&objects[0])[0];
+	let geometry = *pools.borrow::<Mesh>()
+		.borrow(&mesh)
+		.unwrap()
+		.borrow_geometry();
+
+	let mut renderer = match create_renderer().await {
+		Some(renderer) => renderer,
+		None => return,
+	};
+
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+
+	renderer.render(&pools, &scene, &camera).unwrap();
+	let before = renderer.read_pixels().await.unwrap().unwrap();
+
+	let position = *pools.borrow::<Geometry>()
+		.borrow(&geometry)
+		.unwrap()
+		.borrow_attribute("position")
+		.unwrap();
+
+	if let AttributeData::Float32(data) = pools.borrow_mut::<Attribute>()
+		.borrow_mut(&position)
+		.unwrap()
+		.borrow_data_mut() {
+		for value in data.iter_mut() {
+			*value *= 0.5;
+		}
+	}
+
+	let material = MaterialHelper::create_basic_material(
+		&mut pools,
+		Color::set(&mut Color::create(), 0.0, 1.0, 0.0),
+	);
+	pools.borrow_mut::<Mesh>()
+		.borrow_mut(&mesh)
+		.unwrap()
+		.set_material(material);
+
+	renderer.render(&pools, &scene, &camera).unwrap();
+	let after = renderer.read_pixels().await.unwrap().unwrap();
+
+	assert!(before != after, "Edits were not uploaded");
+	compare("update", &after);
+}
+
+// Nodes drawing the same mesh and material share a render pipeline
+#[tokio::test]
+async fn pipeline_cache() {
+	let mut pools = ResourcePools::new();
+	let mut scene = Scene::new();
+
+	let geometry = GeometryHelper::create_box(&mut pools, 0.1, 0.1, 0.1);
+	let material = MaterialHelper::create_basic_material(
+		&mut pools,
+		Color::set(&mut Color::create(), 0.5, 0.5, 1.0),
+	);
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+
+	let mut nodes = Vec::new();
+	for i in 0..100 {
+		let mut node = Node::new();
+		Vector3::set(node.borrow_position_mut(), (i % 10) as f32 * 0.2 - 0.9, (i / 10) as f32 * 0.2 - 0.9, 0.0);
+		let node = pools.borrow_mut::<Node>().add(node);
+		scene.add_node(&node);
+		scene.assign(&node, &mesh);
+		nodes.push(node);
+	}
+
+	let (camera, camera_node) = scenes::create_camera(&mut pools, ASPECT, 3.0);
+	scene.add_node(&camera_node);
+	scene.assign(&camera_node, &camera);
+
+	let scene = pools.borrow_mut::<Scene>().add(scene);
+
+	let mut renderer = match create_renderer().await {
+		Some(renderer) => renderer,
+		None => return,
+	};
+
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+
+	let stats = renderer.get_render_pipeline_stats();
+	assert_eq!(stats.draws, 100);
+	assert_eq!(stats.pipelines, 1);
+	assert_eq!(stats.misses, 1);
+	assert_eq!(stats.hits, 99);
+
+	// A different side needs another pipeline
+	let material = MaterialHelper::create_basic_material(
+		&mut pools,
+		Color::set(&mut Color::create(), 0.5, 0.5, 1.0),
+	);
+	pools.borrow_mut::<Material>().borrow_mut(&material).unwrap().set_side(Side::DoubleSide);
+	let mesh = pools.borrow_mut::<Mesh>().add(Mesh::new(geometry, material));
+	let node = pools.borrow_mut::<Node>().add(Node::new());
+	pools.borrow_mut::<Scene>().borrow_mut(&scene).unwrap().add_node(&node);
+	pools.borrow_mut::<Scene>().borrow_mut(&scene).unwrap().assign(&node, &mesh);
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().pipelines, 2);
+
+	// Released with the last draw using it
+	pools.borrow_mut::<Scene>().borrow_mut(&scene).unwrap().remove_node(&node);
+	pools.borrow_mut::<Node>().remove(&node);
+	renderer.collect_garbage(&pools);
+	let stats = renderer.get_render_pipeline_stats();
+	assert_eq!(stats.draws, 100);
+	assert_eq!(stats.pipelines, 1);
+}
+
+// Draws whose material refers to a removed node are dropped
+#[tokio::test]
+async fn removed_material_node() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, objects) = scenes::create_cube_scene(&mut pools, ASPECT);
+
+	let mut renderer = match create_renderer().await {
+		Some(renderer) => renderer,
+		None => return,
+	};
+
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().draws, 1);
+
+	let mesh = pools.borrow::<Scene>()
+		.borrow(&scene)
+		.unwrap()
+		.borrow_all_assigned_to::<Mesh>(&objects[0])[0];
+	let material = *pools.borrow::<Mesh>()
+		.borrow(&mesh)
+		.unwrap()
+		.borrow_material_at(0)
+		.unwrap();
+	let color = *pools.borrow::<Material>()
+		.borrow(&material)
+		.unwrap()
+		.borrow_color();
+	pools.borrow_mut::<Box<dyn MaterialNode>>().remove(&color);
+
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().draws, 0);
+}
+
+// Instanced meshes of the same mesh on a node are separate draws even if
+// only one of them has colors
+#[tokio::test]
+async fn instanced_meshes_of_same_mesh() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, node) = create_instancing_scene(&mut pools);
+
+	let instanced_mesh = pools.borrow::<Scene>()
+		.borrow(&scene)
+		.unwrap()
+		.borrow_all_assigned_to::<InstancedMesh>(&node)[0];
+	let mesh = *pools.borrow::<InstancedMesh>()
+		.borrow(&instanced_mesh)
+		.unwrap()
+		.borrow_mesh();
+	let instanced_mesh = pools.borrow_mut::<InstancedMesh>().add(InstancedMesh::new(mesh, 1));
+	pools.borrow_mut::<Scene>().borrow_mut(&scene).unwrap().attach(&node, &instanced_mesh);
+
+	let mut renderer = match create_renderer().await {
+		Some(renderer) => renderer,
+		None => return,
+	};
+
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+
+	let stats = renderer.get_render_pipeline_stats();
+	assert_eq!(stats.draws, 2);
+	assert_eq!(stats.pipelines, 2);
+
+	pools.borrow_mut::<Scene>().borrow_mut(&scene).unwrap().detach(&node, &instanced_mesh);
+	pools.borrow_mut::<InstancedMesh>().remove(&instanced_mesh);
+	renderer.collect_garbage(&pools);
+	let stats = renderer.get_render_pipeline_stats();
+	assert_eq!(stats.draws, 1);
+	assert_eq!(stats.pipelines, 1);
+}
+
+// The vertex layout is rebuilt only when the geometry attributes have been
+// changed, and the pipeline only when the layout differs
+#[tokio::test]
+async fn vertex_layout_cache() {
+	let mut pools = ResourcePools::new();
+	let (scene, camera, objects) = scenes::create_triangle_scene(&mut pools, ASPECT);
+
+	let mesh = pools.borrow::<Scene>()
+		.borrow(&scene)
+		.unwrap()
+		.borrow_all_assigned_to::<Mesh>(&objects[0])[0];
+	let geometry = *pools.borrow::<Mesh>()
+		.borrow(&mesh)
+		.unwrap()
+		.borrow_geometry();
+	let position = *pools.borrow::<Geometry>()
+		.borrow(&geometry)
+		.unwrap()
+		.borrow_attribute("position")
+		.unwrap();
+
+	let mut renderer = match create_renderer().await {
+		Some(renderer) => renderer,
+		None => return,
+	};
+
+	SceneExecutor::update_matrices(&mut pools, &scene).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().misses, 1);
+
+	// Same layout
+	pools.borrow_mut::<Attribute>().borrow_mut(&position).unwrap().borrow_data_mut();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().misses, 1);
+
+	// New vertex input
+	let color = pools.borrow_mut::<Attribute>().add(Attribute::new(vec![1.0; 9], 3));
+	pools.borrow_mut::<Geometry>()
+		.borrow_mut(&geometry)
+		.unwrap()
+		.set_attribute("color", color);
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().misses, 2);
+
+	// Skipped once removed from the pool. The pipeline of the first
+	// layout has been released since no draw used it.
+	pools.borrow_mut::<Attribute>().remove(&color);
+	renderer.render(&pools, &scene, &camera).unwrap();
+	renderer.render(&pools, &scene, &camera).unwrap();
+	assert_eq!(renderer.get_render_pipeline_stats().misses, 3);
+}
@@ -0,0 +1,142 @@
+use wgpu_rust_renderer::{
+	error::error::Error,
+	math::vector3::Vector3,
+	resource::resource::{
+		ResourceId,
+		ResourcePool,
+		ResourcePools,
+	},
+	scene::{
+		node::{
+			Node,
+			NodeExecutor,
+		},
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
+	},
+};
+
+fn create_node(pool: &mut ResourcePool<Node>, x: f32) -> ResourceId<Node> {
+	let mut node = Node::new();
+	Vector3::set(node.borrow_position_mut(), x, 0.0, 0.0);
+	pool.add(node)
+}
+
+#[test]
+fn add_and_remove_child() {
+	let mut pool = ResourcePool::<Node>::new();
+	let a = create_node(&mut pool, 0.0);
+	let b = create_node(&mut pool, 0.0);
+	let c = create_node(&mut pool, 0.0);
+
+	NodeExecutor::add_child(&mut pool, &a, &c).unwrap();
+	assert!(pool.borrow(&a).unwrap().borrow_children() == &vec![c]);
+	assert!(pool.borrow(&c).unwrap().borrow_parent() == Some(&a));
+
+	// Reparenting detaches from the old parent
+	NodeExecutor::add_child(&mut pool, &b, &c).unwrap();
+	assert!(pool.borrow(&a).unwrap().borrow_children().is_empty());
+	assert!(pool.borrow(&b).unwrap().borrow_children() == &vec![c]);
+	assert!(pool.borrow(&c).unwrap().borrow_parent() == Some(&b));
+
+	// Not a child of a
+	NodeExecutor::remove_child(&mut pool, &a, &c).unwrap();
+	assert!(pool.borrow(&c).unwrap().borrow_parent() == Some(&b));
+
+	NodeExecutor::remove_child(&mut pool, &b, &c).unwrap();
+	assert!(pool.borrow(&b).unwrap().borrow_children().is_empty());
+	assert!(pool.borrow(&c).unwrap().borrow_parent().is_none());
+}
+
+#[test]
+fn cycle() {
+	let mut pool = ResourcePool::<Node>::new();
+	let a = create_node(&mut pool, 0.0);
+	let b = create_node(&mut pool, 0.0);
+	let c = create_node(&mut pool, 0.0);
+
+	NodeExecutor::add_child(&mut pool, &a, &b).unwrap();
+	NodeExecutor::add_child(&mut pool, &b, &c).unwrap();
+
+	assert!(matches!(NodeExecutor::add_child(&mut pool, &c, &a), Err(Error::HierarchyCycle)));
+	assert!(matches!(NodeExecutor::add_child(&mut pool, &a, &a), Err(Error::HierarchyCycle)));
+	assert!(pool.borrow(&a).unwrap().borrow_parent().is_none());
+}
+
+#[test]
+fn keep_world_transform() {
+	let mut pool = ResourcePool::<Node>::new();
+	let a = create_node(&mut pool, 1.0);
+	let b = create_node(&mut pool, 3.0);
+
+	NodeExecutor::set_parent(&mut pool, &b, Some(&a), true).unwrap();
+	assert!((pool.borrow(&b).unwrap().borrow_position()[0] - 2.0).abs() < 1e-6);
+
+	NodeExecutor::update_matrices(&mut pool, &a);
+	assert!((pool.borrow(&b).unwrap().borrow_world_matrix()[12] - 3.0).abs() < 1e-6);
+
+	NodeExecutor::set_parent(&mut pool, &b, None, true).unwrap();
+	assert!((pool.borrow(&b).unwrap().borrow_position()[0] - 3.0).abs() < 1e-6);
+
+	// Without keeping, the local transform is kept
+	NodeExecutor::set_parent(&mut pool, &b, Some(&a), false).unwrap();
+	assert!((pool.borrow(&b).unwrap().borrow_position()[0] - 3.0).abs() < 1e-6);
+}
+
+// Removing nodes from the pool leaves their ids in the hierarchy
+#[test]
+fn removed_nodes() {
+	let mut pool = ResourcePool::<Node>::new();
+	let a = create_node(&mut pool, 1.0);
+	let b = create_node(&mut pool, 2.0);
+	let c = create_node(&mut pool, 3.0);
+	let d = create_node(&mut pool, 4.0);
+
+	NodeExecutor::add_child(&mut pool, &a, &b).unwrap();
+	NodeExecutor::add_child(&mut pool, &b, &c).unwrap();
+	NodeExecutor::add_child(&mut pool, &a, &d).unwrap();
+	pool.remove(&b);
+
+	let mut nodes = Vec::new();
+	NodeExecutor::collect_nodes(&pool, &a, &mut nodes);
+	assert!(nodes == vec![a, d]);
+
+	NodeExecutor::update_matrices(&mut pool, &a);
+	assert!((pool.borrow(&d).unwrap().borrow_world_matrix()[12] - 5.0).abs() < 1e-6);
+
+	// Orphaned c is a root now
+	NodeExecutor::update_matrices(&mut pool, &c);
+	assert!((pool.borrow(&c).unwrap().borrow_world_matrix()[12] - 3.0).abs() < 1e-6);
+	NodeExecutor::set_parent(&mut pool, &c, Some(&d), true).unwrap();
+	assert!((pool.borrow(&c).unwrap().borrow_position()[0] + 2.0).abs() < 1e-6);
+	assert!(pool.borrow(&d).unwrap().borrow_children() == &vec![c]);
+
+	pool.remove(&a);
+	let mut nodes = Vec::new();
+	NodeExecutor::collect_nodes(&pool, &a, &mut nodes);
+	assert!(nodes.is_empty());
+	NodeExecutor::update_matrices(&mut pool, &a);
+}
+
+// Reparented roots are drawn once
+#[test]
+fn scene_roots() {
+	let mut pools = ResourcePools::new();
+	let a = create_node(pools.borrow_mut::<Node>(), 0.0);
+	let b = create_node(pools.borrow_mut::<Node>(), 0.0);
+
+	let mut scene = Scene::new();
+	scene.add_node(&a);
+	scene.add_node(&b);
+	let scene = pools.borrow_mut::<Scene>().add(scene);
+
+	SceneExecutor::add_child(&mut pools, &scene, &a, &b).unwrap();
+	assert!(pools.borrow::<Node>().borrow(&b).unwrap().borrow_parent() == Some(&a));
+	assert!(pools.borrow::<Scene>().borrow(&scene).unwrap().collect_nodes(&pools) == vec![a, b]);
+
+	SceneExecutor::set_parent(&mut pools, &scene, &b, None, false).unwrap();
+	assert!(pools.borrow::<Scene>().borrow(&scene).unwrap().collect_nodes(&pools) == vec![a, b]);
+	assert!(pools.borrow::<Node>().borrow(&a).unwrap().borrow_children().is_empty());
+}
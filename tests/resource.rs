@@ -0,0 +1,200 @@
+use wgpu_rust_renderer::{
+	error::error::Error,
+	geometry::{
+		geometry::Geometry,
+		vertex_layout::VertexLayout,
+	},
+	material::{
+		material::{
+			Material,
+			Side,
+		},
+		node::{
+			multiply::MultiplyNode,
+			node::MaterialNode,
+			vector3::Vector3Node,
+		},
+	},
+	math::color::ColorSpace,
+	resource::resource::{
+		ResourcePool,
+		ResourcePools,
+	},
+	scene::{
+		node::Node,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
+	},
+	utils::{
+		geometry_helper::GeometryHelper,
+		gltf_loader::{
+			GltfAsset,
+			GltfLoader,
+		},
+	},
+};
+
+#[test]
+fn remove() {
+	let mut pool = ResourcePool::<u32>::new();
+	let a = pool.add(1);
+	let b = pool.add(2);
+
+	assert_eq!(pool.remove(&a), Some(1));
+	assert_eq!(pool.remove(&a), None);
+	assert_eq!(pool.borrow(&a), None);
+	assert_eq!(pool.borrow(&b), Some(&2));
+	assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn stale_id() {
+	let mut pool = ResourcePool::<u32>::new();
+	let a = pool.add(1);
+	pool.remove(&a);
+
+	// The slot is reused but the stale id doesn't alias the new resource
+	let c = pool.add(3);
+	assert_eq!(a.id, c.id);
+	assert!(a != c);
+	assert_eq!(pool.borrow(&a), None);
+	assert_eq!(pool.borrow_mut(&a), None);
+	assert_eq!(pool.borrow(&c), Some(&3));
+}
+
+#[test]
+fn iter() {
+	let mut pool = ResourcePool::<u32>::new();
+	let a = pool.add(1);
+	let b = pool.add(2);
+	let c = pool.add(3);
+	pool.remove(&b);
+
+	let live = pool.iter().collect::<Vec<_>>();
+	assert!(live == vec![(a, &1), (c, &3)]);
+
+	for (_, value) in pool.iter_mut() {
+		*value *= 10;
+	}
+	assert_eq!(pool.borrow(&a), Some(&10));
+	assert_eq!(pool.borrow(&c), Some(&30));
+}
+
+// A material referring to a removed node fails to build instead of panicking
+#[test]
+fn removed_material_node() {
+	let mut pools = ResourcePools::new();
+	let geometry = GeometryHelper::create_box(&mut pools, 1.0, 1.0, 1.0);
+	let vertex_layout = VertexLayout::from_geometry(
+		pools.borrow::<Geometry>().borrow(&geometry).unwrap(),
+		&pools,
+	);
+
+	let (color, material) = {
+		let nodes = pools.borrow_mut::<Box<dyn MaterialNode>>();
+		let color = nodes.add(Box::new(Vector3Node::new([1.0, 0.0, 0.0])));
+		let scale = nodes.add(Box::new(Vector3Node::new([0.5, 0.5, 0.5])));
+		let multiply = nodes.add(Box::new(MultiplyNode::new(color, scale)));
+		(color, Material::new(multiply, Side::default()))
+	};
+
+	let nodes = pools.borrow::<Box<dyn MaterialNode>>();
+	assert!(material.build_shader_code(nodes, &ColorSpace::Linear, &vertex_layout).is_ok());
+	assert_eq!(material.borrow_contents(nodes).unwrap().len(), 2);
+
+	pools.borrow_mut::<Box<dyn MaterialNode>>().remove(&color);
+
+	let nodes = pools.borrow::<Box<dyn MaterialNode>>();
+	assert!(matches!(
+		material.build_shader_code(nodes, &ColorSpace::Linear, &vertex_layout),
+		Err(Error::ResourceNotFound("MaterialNode")),
+	));
+	assert!(material.borrow_contents(nodes).is_err());
+	assert!(material.borrow_textures(nodes).is_err());
+}
+
+fn assert_send_sync<T: Send + Sync>() {
+}
+
+#[test]
+fn thread_safety() {
+	assert_send_sync::<ResourcePools>();
+	assert_send_sync::<GltfAsset>();
+}
+
+// Reads on a worker thread and builds on the thread owning the pools
+#[tokio::test(flavor = "multi_thread")]
+async fn load_on_worker_thread() {
+	let asset = tokio::spawn(async {
+		GltfLoader::read_gltf(
+			concat!(env!("CARGO_MANIFEST_DIR"), "/examples/gltf/assets/"),
+			"DamagedHelmet.gltf",
+		).await
+	}).await.unwrap().unwrap();
+
+	let mut pools = ResourcePools::new();
+	let scene = pools.borrow_mut::<Scene>().add(Scene::new());
+	let nodes = GltfLoader::build(&mut pools, &scene, &asset).unwrap();
+	assert!(!nodes.is_empty());
+	assert!(SceneExecutor::update_matrices(&mut pools, &scene).is_ok());
+}
+
+struct Light {
+	intensity: f32,
+}
+
+#[test]
+fn register() {
+	let mut pools = ResourcePools::new();
+	assert!(pools.try_borrow::<Light>().is_err());
+
+	pools.register::<Light>();
+	let light = pools.borrow_mut::<Light>().add(Light { intensity: 2.0 });
+
+	// Registering again keeps the resources
+	pools.register::<Light>();
+	assert_eq!(pools.borrow::<Light>().borrow(&light).unwrap().intensity, 2.0);
+
+	let node = pools.borrow_mut::<Node>().add(Node::new());
+	let mut scene = Scene::new();
+	scene.add_node(&node);
+	scene.assign(&node, &light);
+	assert!(scene.borrow_assigned_to::<Light>(&node) == Some(&light));
+	assert!(scene.borrow_assigned_from::<Light>(&light) == Some(&node));
+
+	assert!(scene.unassign::<Light>(&node) == vec![light]);
+	assert!(scene.borrow_assigned_to::<Light>(&node).is_none());
+	assert!(scene.borrow_assigned_from::<Light>(&light).is_none());
+}
+
+#[test]
+fn attach() {
+	let mut pools = ResourcePools::new();
+	pools.register::<Light>();
+	let a = pools.borrow_mut::<Light>().add(Light { intensity: 1.0 });
+	let b = pools.borrow_mut::<Light>().add(Light { intensity: 2.0 });
+	let c = pools.borrow_mut::<Light>().add(Light { intensity: 3.0 });
+
+	let node = pools.borrow_mut::<Node>().add(Node::new());
+	let mut scene = Scene::new();
+	scene.add_node(&node);
+	scene.attach(&node, &a);
+	scene.attach(&node, &b);
+	scene.attach(&node, &b);
+	assert!(scene.borrow_all_assigned_to::<Light>(&node) == &[a, b]);
+	assert!(scene.borrow_assigned_to::<Light>(&node) == Some(&a));
+	assert!(scene.borrow_assigned_from::<Light>(&b) == Some(&node));
+
+	assert!(scene.detach(&node, &a));
+	assert!(!scene.detach(&node, &a));
+	assert!(scene.borrow_all_assigned_to::<Light>(&node) == &[b]);
+	assert!(scene.borrow_assigned_from::<Light>(&a).is_none());
+
+	// Assigning replaces all
+	scene.attach(&node, &a);
+	scene.assign(&node, &c);
+	assert!(scene.borrow_all_assigned_to::<Light>(&node) == &[c]);
+	assert!(scene.borrow_assigned_from::<Light>(&b).is_none());
+}
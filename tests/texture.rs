@@ -0,0 +1,70 @@
+use wgpu_rust_renderer::{
+	texture::texture::TextureFormat,
+	utils::texture_loader::TextureLoader,
+};
+
+fn encode_png(
+	width: u32,
+	height: u32,
+	color_type: png::ColorType,
+	bit_depth: png::BitDepth,
+	data: &[u8],
+) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	{
+		let mut encoder = png::Encoder::new(&mut bytes, width, height);
+		encoder.set_color(color_type);
+		encoder.set_depth(bit_depth);
+		encoder.write_header().unwrap().write_image_data(data).unwrap();
+	}
+	bytes
+}
+
+#[test]
+fn decode_rgb_png() {
+	let bytes = encode_png(
+		2,
+		1,
+		png::ColorType::Rgb,
+		png::BitDepth::Eight,
+		&[255, 0, 0, 0, 128, 255],
+	);
+
+	let texture = TextureLoader::decode_png(bytes.as_slice(), TextureFormat::default()).unwrap();
+	assert_eq!(texture.get_width(), 2);
+	assert_eq!(texture.get_height(), 1);
+	assert_eq!(texture.borrow_texels(), &vec![255, 0, 0, 255, 0, 128, 255, 255]);
+}
+
+#[test]
+fn decode_grayscale_png() {
+	let bytes = encode_png(
+		2,
+		2,
+		png::ColorType::Grayscale,
+		png::BitDepth::Eight,
+		&[0, 64, 128, 255],
+	);
+
+	let texture = TextureLoader::decode_png(bytes.as_slice(), TextureFormat::default()).unwrap();
+	assert_eq!(texture.get_width(), 2);
+	assert_eq!(texture.get_height(), 2);
+	assert_eq!(texture.borrow_texels(), &vec![
+		0, 0, 0, 255,
+		64, 64, 64, 255,
+		128, 128, 128, 255,
+		255, 255, 255, 255,
+	]);
+
+	// 16 bits are stripped to 8 bits
+	let bytes = encode_png(
+		1,
+		1,
+		png::ColorType::GrayscaleAlpha,
+		png::BitDepth::Sixteen,
+		&[0x80, 0x00, 0xff, 0xff],
+	);
+
+	let texture = TextureLoader::decode_png(bytes.as_slice(), TextureFormat::default()).unwrap();
+	assert_eq!(texture.borrow_texels(), &vec![128, 128, 128, 255]);
+}
@@ -23,7 +23,10 @@ use wgpu_rust_renderer::{
 	scene::{
 		camera::PerspectiveCamera,
 		node::Node,
-		scene::Scene,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
 	},
 	utils::gltf_loader::GltfLoader,
 	web::wgpu_web_renderer::WGPUWebRenderer,
@@ -41,7 +44,7 @@ async fn create_scene(
 		// Path from index.html
 		"./assets/",
 		"DamagedHelmet.gltf",
-	).await;
+	).await.unwrap();
 
 	for node in nodes.iter() {
 		pools.borrow_mut::<Scene>()
@@ -110,10 +113,7 @@ fn update(
 		);
 	}
 
-	pools.borrow::<Scene>()
-		.borrow(scene)
-		.unwrap()
-		.update_matrices(pools);
+	SceneExecutor::update_matrices(pools, scene).unwrap();
 }
 
 fn render(
@@ -122,7 +122,7 @@ fn render(
 	scene: &ResourceId<Scene>,
 	camera: &ResourceId<PerspectiveCamera>,
 ) {
-	renderer.render(pools, scene, camera);
+	renderer.render(pools, scene, camera).unwrap();
 }
 
 #[wasm_bindgen(start)]
@@ -147,7 +147,7 @@ pub async fn start() {
 	let inner_size = get_window_inner_size();
 	let pixel_ratio = get_window_device_pixel_ratio();
 
-	let mut renderer = WGPUWebRenderer::new(&window, window.canvas(), WGPURendererOptions::default()).await;
+	let mut renderer = WGPUWebRenderer::new(&window, window.canvas(), WGPURendererOptions::default()).await.unwrap();
 	renderer.set_size(inner_size.0 as f64, inner_size.1 as f64);
 	renderer.set_pixel_ratio(pixel_ratio as f64);
 
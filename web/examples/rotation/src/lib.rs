@@ -24,7 +24,10 @@ use wgpu_rust_renderer::{
 		camera::PerspectiveCamera,
 		mesh::Mesh,
 		node::Node,
-		scene::Scene,
+		scene::{
+			Scene,
+			SceneExecutor,
+		},
 	},
 	utils::{
 		geometry_helper::GeometryHelper,
@@ -101,10 +104,7 @@ fn update(
 		node.borrow_rotation_mut()[2] += 0.01;
 	}
 
-	pools.borrow::<Scene>()
-		.borrow(scene)
-		.unwrap()
-		.update_matrices(pools);
+	SceneExecutor::update_matrices(pools, scene).unwrap();
 }
 
 fn render(
@@ -113,7 +113,7 @@ fn render(
 	scene: &ResourceId<Scene>,
 	camera: &ResourceId<PerspectiveCamera>,
 ) {
-	renderer.render(pools, scene, camera);
+	renderer.render(pools, scene, camera).unwrap();
 }
 
 #[wasm_bindgen(start)]
@@ -138,7 +138,7 @@ pub async fn start() {
 	let inner_size = get_window_inner_size();
 	let pixel_ratio = get_window_device_pixel_ratio();
 
-	let mut renderer = WGPUWebRenderer::new(&window, window.canvas(), WGPURendererOptions::default()).await;
+	let mut renderer = WGPUWebRenderer::new(&window, window.canvas(), WGPURendererOptions::default()).await.unwrap();
 	renderer.set_size(inner_size.0 as f64, inner_size.1 as f64);
 	renderer.set_pixel_ratio(pixel_ratio as f64);
 